@@ -0,0 +1 @@
+ALTER TABLE recipe ADD COLUMN servings integer NOT NULL DEFAULT 1;
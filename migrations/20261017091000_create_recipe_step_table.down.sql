@@ -0,0 +1,2 @@
+DROP TABLE recipe_step_ingredient;
+DROP TABLE recipe_step;
@@ -0,0 +1,16 @@
+CREATE TABLE recipe_step (
+    id SERIAL PRIMARY KEY,
+    recipe_id integer NOT NULL,
+    text character varying NOT NULL,
+    duration integer,
+    sort integer NOT NULL,
+    CONSTRAINT recipe_step_recipe_id_fkey FOREIGN KEY(recipe_id) REFERENCES recipe(id) ON DELETE CASCADE
+);
+
+CREATE TABLE recipe_step_ingredient (
+    id SERIAL PRIMARY KEY,
+    recipe_step_id integer NOT NULL,
+    ingredient_id integer NOT NULL,
+    CONSTRAINT recipe_step_ingredient_recipe_step_id_fkey FOREIGN KEY(recipe_step_id) REFERENCES recipe_step(id) ON DELETE CASCADE,
+    CONSTRAINT recipe_step_ingredient_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE recipe_quantity ALTER COLUMN quantity TYPE integer USING ceil(quantity);
+ALTER TABLE shopping_quantity ALTER COLUMN quantity TYPE integer USING ceil(quantity);
@@ -0,0 +1,2 @@
+ALTER TABLE recipe_quantity ALTER COLUMN quantity TYPE numeric(12, 4);
+ALTER TABLE shopping_quantity ALTER COLUMN quantity TYPE numeric(12, 4);
@@ -0,0 +1,5 @@
+ALTER TABLE shopping_quantity DROP COLUMN unit_id;
+ALTER TABLE recipe_quantity DROP COLUMN unit_id;
+ALTER TABLE ingredient DROP COLUMN density;
+ALTER TABLE unit DROP COLUMN factor;
+ALTER TABLE unit DROP COLUMN family;
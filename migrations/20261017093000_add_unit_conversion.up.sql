@@ -0,0 +1,26 @@
+ALTER TABLE unit ADD COLUMN family character varying CHECK (family IN ('mass', 'volume', 'count'));
+-- Amount of the family's base unit (g, ml, piece) one unit equals
+ALTER TABLE unit ADD COLUMN factor numeric(16, 6) NOT NULL DEFAULT 1;
+
+UPDATE unit SET family = 'mass', factor = 1 WHERE name = 'g';
+UPDATE unit SET family = 'mass', factor = 1000 WHERE name = 'kg';
+UPDATE unit SET family = 'volume', factor = 1 WHERE name = 'ml';
+UPDATE unit SET family = 'volume', factor = 1000 WHERE name = 'l';
+UPDATE unit SET family = 'count', factor = 1 WHERE name = 'none';
+
+-- Grams per millilitre, allows converting between mass and volume
+ALTER TABLE ingredient ADD COLUMN density numeric(12, 4);
+
+-- Deleting a unit mustn't silently delete the quantities stored in it
+ALTER TABLE recipe_quantity ADD COLUMN unit_id integer;
+UPDATE recipe_quantity AS rq SET unit_id = i.unit_id FROM ingredient AS i WHERE rq.ingredient_id = i.id;
+ALTER TABLE recipe_quantity ALTER COLUMN unit_id SET NOT NULL;
+ALTER TABLE recipe_quantity ADD CONSTRAINT recipe_quantity_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE RESTRICT;
+
+ALTER TABLE shopping_quantity ADD COLUMN unit_id integer;
+UPDATE shopping_quantity AS sq SET unit_id = i.unit_id
+FROM shopping_ingredient AS si
+JOIN ingredient AS i ON si.ingredient_id = i.id
+WHERE sq.shopping_ingredient_id = si.id;
+ALTER TABLE shopping_quantity ALTER COLUMN unit_id SET NOT NULL;
+ALTER TABLE shopping_quantity ADD CONSTRAINT shopping_quantity_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE RESTRICT;
@@ -0,0 +1,5 @@
+ALTER TABLE ingredient DROP CONSTRAINT ingredient_unit_id_fkey;
+ALTER TABLE ingredient ADD CONSTRAINT ingredient_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE CASCADE;
+
+DELETE FROM unit WHERE user_id IS NOT NULL;
+ALTER TABLE unit DROP COLUMN user_id;
@@ -0,0 +1,7 @@
+-- Units without a user are available to everyone
+ALTER TABLE unit ADD COLUMN user_id uuid;
+ALTER TABLE unit ADD CONSTRAINT unit_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE;
+
+-- Deleting a custom unit mustn't silently delete the ingredients using it
+ALTER TABLE ingredient DROP CONSTRAINT ingredient_unit_id_fkey;
+ALTER TABLE ingredient ADD CONSTRAINT ingredient_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE RESTRICT;
@@ -0,0 +1,4 @@
+DROP AGGREGATE tsvector_agg (tsvector);
+ALTER TABLE recipe_step DROP COLUMN search;
+ALTER TABLE ingredient DROP COLUMN search;
+ALTER TABLE recipe DROP COLUMN search;
@@ -0,0 +1,13 @@
+ALTER TABLE recipe ADD COLUMN search tsvector GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED;
+ALTER TABLE ingredient ADD COLUMN search tsvector GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED;
+ALTER TABLE recipe_step ADD COLUMN search tsvector GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED;
+
+CREATE INDEX recipe_search_idx ON recipe USING GIN (search);
+CREATE INDEX ingredient_search_idx ON ingredient USING GIN (search);
+CREATE INDEX recipe_step_search_idx ON recipe_step USING GIN (search);
+
+CREATE AGGREGATE tsvector_agg (tsvector) (
+    SFUNC = tsvector_concat,
+    STYPE = tsvector,
+    INITCOND = ''
+);
@@ -0,0 +1,15 @@
+CREATE TABLE tag (
+    id SERIAL PRIMARY KEY,
+    user_id uuid NOT NULL,
+    name character varying NOT NULL,
+    CONSTRAINT tag_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE
+);
+
+CREATE TABLE recipe_tag (
+    id SERIAL PRIMARY KEY,
+    recipe_id integer NOT NULL,
+    tag_id integer NOT NULL,
+    CONSTRAINT recipe_tag_recipe_id_tag_id_key UNIQUE(recipe_id, tag_id),
+    CONSTRAINT recipe_tag_recipe_id_fkey FOREIGN KEY(recipe_id) REFERENCES recipe(id) ON DELETE CASCADE,
+    CONSTRAINT recipe_tag_tag_id_fkey FOREIGN KEY(tag_id) REFERENCES tag(id) ON DELETE CASCADE
+);
@@ -0,0 +1,14 @@
+CREATE TABLE meal_plan (
+    id SERIAL PRIMARY KEY,
+    user_id uuid NOT NULL,
+    recipe_id integer NOT NULL,
+    date date NOT NULL,
+    slot character varying NOT NULL,
+    servings integer NOT NULL,
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT meal_plan_slot_check CHECK (slot IN ('breakfast', 'lunch', 'dinner', 'snack')),
+    CONSTRAINT meal_plan_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT meal_plan_recipe_id_fkey FOREIGN KEY(recipe_id) REFERENCES recipe(id) ON DELETE CASCADE
+);
+
+CREATE INDEX meal_plan_user_id_date_idx ON meal_plan(user_id, date);
@@ -0,0 +1,13 @@
+CREATE TABLE pantry (
+    id SERIAL PRIMARY KEY,
+    user_id uuid NOT NULL,
+    ingredient_id integer NOT NULL,
+    quantity numeric(12, 4) NOT NULL,
+    unit_id integer NOT NULL,
+    expires_at date,
+    CONSTRAINT pantry_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT pantry_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE,
+    CONSTRAINT pantry_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id)
+);
+
+CREATE INDEX pantry_user_id_ingredient_id_idx ON pantry(user_id, ingredient_id);
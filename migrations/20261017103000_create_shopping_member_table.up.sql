@@ -0,0 +1,12 @@
+CREATE TABLE shopping_member (
+    id SERIAL PRIMARY KEY,
+    shopping_id integer NOT NULL,
+    user_id uuid NOT NULL,
+    role character varying NOT NULL,
+    CONSTRAINT shopping_member_role_check CHECK (role IN ('editor', 'viewer')),
+    CONSTRAINT shopping_member_shopping_id_user_id_key UNIQUE(shopping_id, user_id),
+    CONSTRAINT shopping_member_shopping_id_fkey FOREIGN KEY(shopping_id) REFERENCES shopping(id) ON DELETE CASCADE,
+    CONSTRAINT shopping_member_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE
+);
+
+CREATE INDEX shopping_member_user_id_idx ON shopping_member(user_id);
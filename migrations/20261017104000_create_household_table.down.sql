@@ -0,0 +1,11 @@
+DROP FUNCTION shopping_users;
+DROP FUNCTION co_members;
+DROP FUNCTION in_library;
+DROP FUNCTION has_access;
+
+ALTER TABLE shopping DROP COLUMN household_id;
+ALTER TABLE ingredient DROP COLUMN household_id;
+ALTER TABLE recipe DROP COLUMN household_id;
+
+DROP TABLE household_member;
+DROP TABLE household;
@@ -0,0 +1,71 @@
+CREATE TABLE household (
+    id SERIAL PRIMARY KEY,
+    name character varying NOT NULL,
+    created_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE TABLE household_member (
+    id SERIAL PRIMARY KEY,
+    household_id integer NOT NULL,
+    user_id uuid NOT NULL,
+    role character varying NOT NULL,
+    CONSTRAINT household_member_role_check CHECK (role IN ('admin', 'member')),
+    CONSTRAINT household_member_household_id_user_id_key UNIQUE(household_id, user_id),
+    CONSTRAINT household_member_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE CASCADE,
+    CONSTRAINT household_member_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE
+);
+
+CREATE INDEX household_member_user_id_idx ON household_member(user_id);
+
+-- Deleting a household hands its data back to the users who created it
+ALTER TABLE recipe ADD COLUMN household_id integer;
+ALTER TABLE recipe ADD CONSTRAINT recipe_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE SET NULL;
+
+ALTER TABLE ingredient ADD COLUMN household_id integer;
+ALTER TABLE ingredient ADD CONSTRAINT ingredient_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE SET NULL;
+
+ALTER TABLE shopping ADD COLUMN household_id integer;
+ALTER TABLE shopping ADD CONSTRAINT shopping_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE SET NULL;
+
+-- Household rows belong to all members, personal rows only to their user
+CREATE FUNCTION has_access(owner_id uuid, owner_household_id integer, viewer_id uuid)
+RETURNS boolean AS $$
+    SELECT CASE
+        WHEN owner_household_id IS NULL THEN owner_id = viewer_id
+        ELSE EXISTS (
+            SELECT 1 FROM household_member
+            WHERE household_id = owner_household_id AND user_id = viewer_id
+        )
+    END
+$$ LANGUAGE sql STABLE;
+
+-- Whether a row is in the personal library of a user or in the library of a household
+CREATE FUNCTION in_library(owner_id uuid, owner_household_id integer, library_user_id uuid, library_household_id integer)
+RETURNS boolean AS $$
+    SELECT CASE
+        WHEN library_household_id IS NULL THEN owner_household_id IS NULL AND owner_id = library_user_id
+        ELSE owner_household_id = library_household_id
+    END
+$$ LANGUAGE sql STABLE;
+
+-- The user and everyone sharing a household with them
+CREATE FUNCTION co_members(member_id uuid)
+RETURNS SETOF uuid AS $$
+    SELECT member_id
+    UNION
+    SELECT hm2.user_id FROM household_member AS hm
+    JOIN household_member AS hm2 ON hm.household_id = hm2.household_id
+    WHERE hm.user_id = member_id
+$$ LANGUAGE sql STABLE;
+
+-- Everyone with access to a shopping list: its owner, its members and the members of its household
+CREATE FUNCTION shopping_users(list_id integer)
+RETURNS SETOF uuid AS $$
+    SELECT user_id FROM shopping WHERE id = list_id
+    UNION
+    SELECT user_id FROM shopping_member WHERE shopping_id = list_id
+    UNION
+    SELECT hm.user_id FROM household_member AS hm
+    JOIN shopping AS s ON hm.household_id = s.household_id
+    WHERE s.id = list_id
+$$ LANGUAGE sql STABLE;
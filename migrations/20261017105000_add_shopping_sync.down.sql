@@ -0,0 +1,16 @@
+DROP TRIGGER shopping_quantity_changed ON shopping_quantity;
+DROP FUNCTION shopping_quantity_changed;
+DROP TRIGGER shopping_ingredient_deleted ON shopping_ingredient;
+DROP FUNCTION shopping_ingredient_deleted;
+DROP TRIGGER shopping_ingredient_removed_version ON shopping_ingredient_removed;
+DROP FUNCTION shopping_ingredient_removed_version;
+DROP TRIGGER shopping_ingredient_changed ON shopping_ingredient;
+DROP FUNCTION shopping_ingredient_changed;
+DROP TABLE shopping_sync_operation;
+DROP TABLE shopping_ingredient_removed;
+DROP FUNCTION next_shopping_version;
+DROP INDEX shopping_ingredient_shopping_id_version_idx;
+ALTER TABLE shopping_ingredient DROP COLUMN updated_at;
+ALTER TABLE shopping_ingredient DROP COLUMN version;
+ALTER TABLE shopping DROP COLUMN pruned_version;
+ALTER TABLE shopping DROP COLUMN version;
@@ -0,0 +1,95 @@
+-- Every change of a shopping ingredient gets a new version, clients sync everything newer than
+-- the last version they've seen. Versions are counted per shopping list under the list's row
+-- lock, so they become visible in the order they're handed out and a sync token can't skip a
+-- change which commits later.
+ALTER TABLE shopping ADD COLUMN version bigint NOT NULL DEFAULT 0;
+
+ALTER TABLE shopping_ingredient ADD COLUMN version bigint NOT NULL DEFAULT 1;
+ALTER TABLE shopping_ingredient ADD COLUMN updated_at timestamp with time zone NOT NULL DEFAULT now();
+
+-- Removals up to this version were pruned, older sync tokens get the whole list again
+ALTER TABLE shopping ADD COLUMN pruned_version bigint NOT NULL DEFAULT 0;
+
+-- Existing shopping ingredients are the first version of their list
+UPDATE shopping SET version = 1;
+ALTER TABLE shopping_ingredient ALTER COLUMN version DROP DEFAULT;
+
+CREATE INDEX shopping_ingredient_shopping_id_version_idx ON shopping_ingredient(shopping_id, version);
+
+CREATE FUNCTION next_shopping_version(list_id integer)
+RETURNS bigint AS $$
+    UPDATE shopping SET version = version + 1 WHERE id = list_id RETURNING version
+$$ LANGUAGE sql;
+
+-- Deleted shopping ingredients, so clients can remove them too
+CREATE TABLE shopping_ingredient_removed (
+    id SERIAL PRIMARY KEY,
+    shopping_id integer NOT NULL,
+    shopping_ingredient_id integer NOT NULL,
+    version bigint NOT NULL,
+    removed_at timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT shopping_ingredient_removed_shopping_id_fkey FOREIGN KEY(shopping_id) REFERENCES shopping(id) ON DELETE CASCADE
+);
+
+CREATE INDEX shopping_ingredient_removed_shopping_id_version_idx ON shopping_ingredient_removed(shopping_id, version);
+CREATE INDEX shopping_ingredient_removed_removed_at_idx ON shopping_ingredient_removed(removed_at);
+
+-- Operations of clients already applied, so retried syncs don't apply them twice
+CREATE TABLE shopping_sync_operation (
+    id uuid NOT NULL,
+    shopping_id integer NOT NULL,
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT shopping_sync_operation_pkey PRIMARY KEY(shopping_id, id),
+    CONSTRAINT shopping_sync_operation_shopping_id_fkey FOREIGN KEY(shopping_id) REFERENCES shopping(id) ON DELETE CASCADE
+);
+
+CREATE INDEX shopping_sync_operation_created_at_idx ON shopping_sync_operation(created_at);
+
+CREATE FUNCTION shopping_ingredient_changed()
+RETURNS trigger AS $$
+BEGIN
+    NEW.version := next_shopping_version(NEW.shopping_id);
+    NEW.updated_at := now();
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER shopping_ingredient_changed BEFORE INSERT OR UPDATE ON shopping_ingredient
+FOR EACH ROW EXECUTE FUNCTION shopping_ingredient_changed();
+
+CREATE FUNCTION shopping_ingredient_removed_version()
+RETURNS trigger AS $$
+BEGIN
+    NEW.version := next_shopping_version(NEW.shopping_id);
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER shopping_ingredient_removed_version BEFORE INSERT ON shopping_ingredient_removed
+FOR EACH ROW EXECUTE FUNCTION shopping_ingredient_removed_version();
+
+CREATE FUNCTION shopping_ingredient_deleted()
+RETURNS trigger AS $$
+BEGIN
+    -- Nothing to remember if the whole list is deleted
+    INSERT INTO shopping_ingredient_removed ( shopping_id, shopping_ingredient_id )
+    SELECT OLD.shopping_id, OLD.id WHERE EXISTS (SELECT 1 FROM shopping WHERE id = OLD.shopping_id);
+    RETURN OLD;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER shopping_ingredient_deleted AFTER DELETE ON shopping_ingredient
+FOR EACH ROW EXECUTE FUNCTION shopping_ingredient_deleted();
+
+-- Quantities are part of their shopping ingredient
+CREATE FUNCTION shopping_quantity_changed()
+RETURNS trigger AS $$
+BEGIN
+    UPDATE shopping_ingredient SET version = version
+    WHERE id = COALESCE(NEW.shopping_ingredient_id, OLD.shopping_ingredient_id);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER shopping_quantity_changed AFTER INSERT OR UPDATE OR DELETE ON shopping_quantity
+FOR EACH ROW EXECUTE FUNCTION shopping_quantity_changed();
@@ -0,0 +1,3 @@
+ALTER TABLE shopping_ingredient DROP CONSTRAINT shopping_ingredient_checked_by_fkey;
+ALTER TABLE shopping_ingredient DROP COLUMN checked_at;
+ALTER TABLE shopping_ingredient DROP COLUMN checked_by;
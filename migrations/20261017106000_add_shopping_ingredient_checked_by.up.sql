@@ -0,0 +1,3 @@
+ALTER TABLE shopping_ingredient ADD COLUMN checked_by uuid;
+ALTER TABLE shopping_ingredient ADD COLUMN checked_at timestamp with time zone;
+ALTER TABLE shopping_ingredient ADD CONSTRAINT shopping_ingredient_checked_by_fkey FOREIGN KEY(checked_by) REFERENCES "user"(id) ON DELETE SET NULL;
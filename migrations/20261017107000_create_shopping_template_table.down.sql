@@ -0,0 +1,3 @@
+DROP FUNCTION next_run_after;
+DROP TABLE shopping_template_quantity;
+DROP TABLE shopping_template;
@@ -0,0 +1,46 @@
+CREATE TABLE shopping_template (
+    id SERIAL PRIMARY KEY,
+    name character varying NOT NULL,
+    user_id uuid NOT NULL,
+    household_id integer,
+    recurrence character varying,
+    next_run date,
+    -- Runs are counted from here instead of from the previous run, so monthly runs on the 31st
+    -- only move to the end of shorter months
+    anchor date,
+    created_at timestamp NOT NULL DEFAULT now(),
+    CONSTRAINT shopping_template_recurrence_check CHECK (recurrence IN ('daily', 'weekly', 'monthly')),
+    CONSTRAINT shopping_template_next_run_check CHECK ((recurrence IS NULL) = (next_run IS NULL)),
+    CONSTRAINT shopping_template_anchor_check CHECK ((recurrence IS NULL) = (anchor IS NULL)),
+    CONSTRAINT shopping_template_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT shopping_template_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE SET NULL
+);
+
+CREATE INDEX shopping_template_next_run_idx ON shopping_template(next_run);
+
+CREATE TABLE shopping_template_quantity (
+    id SERIAL PRIMARY KEY,
+    shopping_template_id integer NOT NULL,
+    ingredient_id integer NOT NULL,
+    quantity numeric(12, 4) NOT NULL,
+    unit_id integer NOT NULL,
+    CONSTRAINT shopping_template_quantity_shopping_template_id_fkey FOREIGN KEY(shopping_template_id) REFERENCES shopping_template(id) ON DELETE CASCADE,
+    CONSTRAINT shopping_template_quantity_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE,
+    CONSTRAINT shopping_template_quantity_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE RESTRICT
+);
+
+-- First run of a recurring template after today, missed runs are skipped. Today is passed in by
+-- the server, so the job and the API agree on the date regardless of the database's time zone.
+CREATE FUNCTION next_run_after(recurrence character varying, anchor date, today date)
+RETURNS date AS $$
+    SELECT min(anchor + n * step)::date
+    FROM (
+        SELECT CASE recurrence
+            WHEN 'daily' THEN interval '1 day'
+            WHEN 'weekly' THEN interval '1 week'
+            WHEN 'monthly' THEN interval '1 month'
+        END AS step
+    ) AS steps,
+    generate_series(1, GREATEST(today - anchor + 1, 1)) AS n
+    WHERE anchor + n * step > today
+$$ LANGUAGE sql IMMUTABLE;
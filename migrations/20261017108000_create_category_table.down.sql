@@ -0,0 +1,3 @@
+ALTER TABLE ingredient DROP CONSTRAINT ingredient_category_id_fkey;
+ALTER TABLE ingredient DROP COLUMN category_id;
+DROP TABLE category;
@@ -0,0 +1,15 @@
+CREATE TABLE category (
+    id SERIAL PRIMARY KEY,
+    name character varying NOT NULL,
+    user_id uuid NOT NULL,
+    household_id integer,
+    sort integer NOT NULL,
+    CONSTRAINT category_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT category_household_id_fkey FOREIGN KEY(household_id) REFERENCES household(id) ON DELETE SET NULL
+);
+
+CREATE INDEX category_user_id_idx ON category(user_id);
+CREATE INDEX category_household_id_idx ON category(household_id);
+
+ALTER TABLE ingredient ADD COLUMN category_id integer;
+ALTER TABLE ingredient ADD CONSTRAINT ingredient_category_id_fkey FOREIGN KEY(category_id) REFERENCES category(id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE shopping_ingredient DROP CONSTRAINT shopping_ingredient_store_id_fkey;
+ALTER TABLE shopping_ingredient DROP COLUMN store_id;
+DROP TABLE store_ingredient;
+DROP TABLE store_category;
+DROP TABLE store;
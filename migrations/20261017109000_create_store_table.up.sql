@@ -0,0 +1,29 @@
+CREATE TABLE store (
+    id SERIAL PRIMARY KEY,
+    name character varying NOT NULL,
+    user_id uuid NOT NULL,
+    CONSTRAINT store_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE
+);
+
+CREATE INDEX store_user_id_idx ON store(user_id);
+
+CREATE TABLE store_category (
+    store_id integer NOT NULL,
+    category_id integer NOT NULL,
+    sort integer NOT NULL,
+    PRIMARY KEY(store_id, category_id),
+    CONSTRAINT store_category_store_id_fkey FOREIGN KEY(store_id) REFERENCES store(id) ON DELETE CASCADE,
+    CONSTRAINT store_category_category_id_fkey FOREIGN KEY(category_id) REFERENCES category(id) ON DELETE CASCADE
+);
+
+CREATE TABLE store_ingredient (
+    store_id integer NOT NULL,
+    ingredient_id integer NOT NULL,
+    sort integer NOT NULL,
+    PRIMARY KEY(store_id, ingredient_id),
+    CONSTRAINT store_ingredient_store_id_fkey FOREIGN KEY(store_id) REFERENCES store(id) ON DELETE CASCADE,
+    CONSTRAINT store_ingredient_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE
+);
+
+ALTER TABLE shopping_ingredient ADD COLUMN store_id integer;
+ALTER TABLE shopping_ingredient ADD CONSTRAINT shopping_ingredient_store_id_fkey FOREIGN KEY(store_id) REFERENCES store(id) ON DELETE SET NULL;
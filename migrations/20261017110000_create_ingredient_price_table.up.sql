@@ -0,0 +1,16 @@
+CREATE TABLE ingredient_price (
+    id SERIAL PRIMARY KEY,
+    ingredient_id integer NOT NULL,
+    user_id uuid NOT NULL,
+    store_id integer,
+    price numeric(10, 2) NOT NULL CHECK (price >= 0),
+    quantity numeric(12, 4) NOT NULL CHECK (quantity > 0),
+    unit_id integer NOT NULL,
+    observed_on date NOT NULL DEFAULT current_date,
+    CONSTRAINT ingredient_price_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE,
+    CONSTRAINT ingredient_price_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT ingredient_price_store_id_fkey FOREIGN KEY(store_id) REFERENCES store(id) ON DELETE SET NULL,
+    CONSTRAINT ingredient_price_unit_id_fkey FOREIGN KEY(unit_id) REFERENCES unit(id) ON DELETE RESTRICT
+);
+
+CREATE INDEX ingredient_price_ingredient_id_idx ON ingredient_price(ingredient_id, observed_on DESC);
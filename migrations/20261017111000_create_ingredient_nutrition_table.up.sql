@@ -0,0 +1,11 @@
+CREATE TABLE ingredient_nutrition (
+    ingredient_id integer PRIMARY KEY,
+    per character varying NOT NULL CHECK (per IN ('100g', '100ml', 'piece')),
+    kcal numeric(10, 2) NOT NULL,
+    protein numeric(10, 2) NOT NULL,
+    fat numeric(10, 2) NOT NULL,
+    carbs numeric(10, 2) NOT NULL,
+    fibre numeric(10, 2) NOT NULL,
+    salt numeric(10, 2) NOT NULL,
+    CONSTRAINT ingredient_nutrition_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+DROP FUNCTION recipe_diets;
+DROP FUNCTION recipe_allergens;
+ALTER TABLE ingredient DROP COLUMN diets;
+ALTER TABLE ingredient DROP COLUMN allergens;
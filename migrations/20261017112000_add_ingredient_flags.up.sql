@@ -0,0 +1,28 @@
+ALTER TABLE ingredient ADD COLUMN allergens character varying[] NOT NULL DEFAULT '{}';
+ALTER TABLE ingredient ADD COLUMN diets character varying[] NOT NULL DEFAULT '{}';
+
+-- Allergens contained in any ingredient of a recipe
+CREATE FUNCTION recipe_allergens(flagged_recipe_id integer)
+RETURNS character varying[] AS $$
+    SELECT COALESCE(array_agg(DISTINCT allergen ORDER BY allergen), '{}')
+    FROM recipe_quantity AS rq
+    JOIN ingredient AS i ON rq.ingredient_id = i.id
+    CROSS JOIN unnest(i.allergens) AS allergen
+    WHERE rq.recipe_id = flagged_recipe_id
+$$ LANGUAGE sql STABLE;
+
+-- Diets all ingredients of a recipe are suitable for, none for a recipe without ingredients
+CREATE FUNCTION recipe_diets(flagged_recipe_id integer)
+RETURNS character varying[] AS $$
+    SELECT COALESCE(array_agg(diet ORDER BY diet), '{}') FROM (
+        SELECT diet FROM recipe_quantity AS rq
+        JOIN ingredient AS i ON rq.ingredient_id = i.id
+        CROSS JOIN unnest(i.diets) AS diet
+        WHERE rq.recipe_id = flagged_recipe_id
+        GROUP BY diet
+        HAVING count(DISTINCT i.id) = (
+            SELECT count(DISTINCT ingredient_id) FROM recipe_quantity
+            WHERE recipe_id = flagged_recipe_id
+        )
+    ) AS diets
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,14 @@
+CREATE TABLE substitution (
+    id SERIAL PRIMARY KEY,
+    ingredient_id integer NOT NULL,
+    substitute_id integer NOT NULL,
+    ratio numeric(12, 4) NOT NULL CHECK (ratio > 0),
+    user_id uuid NOT NULL,
+    CONSTRAINT substitution_ingredient_id_fkey FOREIGN KEY(ingredient_id) REFERENCES ingredient(id) ON DELETE CASCADE,
+    CONSTRAINT substitution_substitute_id_fkey FOREIGN KEY(substitute_id) REFERENCES ingredient(id) ON DELETE CASCADE,
+    CONSTRAINT substitution_user_id_fkey FOREIGN KEY(user_id) REFERENCES "user"(id) ON DELETE CASCADE,
+    CONSTRAINT substitution_itself CHECK (ingredient_id <> substitute_id),
+    UNIQUE (user_id, ingredient_id, substitute_id)
+);
+
+CREATE INDEX substitution_ingredient_id_idx ON substitution(ingredient_id);
@@ -1,7 +1,20 @@
 mod auth;
+pub mod categories;
+mod date;
+pub mod flags;
 mod global;
+pub mod households;
 pub mod ingredients;
+pub mod meal_plans;
+pub mod nutrition;
+pub mod pantry;
+pub mod prices;
 pub mod recipes;
+pub mod search;
 pub mod shopping;
+pub mod shopping_templates;
+pub mod stores;
+pub mod substitutions;
+pub mod tags;
 pub mod units;
 pub mod users;
@@ -33,6 +33,10 @@ impl Claims {
     pub fn get_sub(&self) -> Uuid {
         self.sub
     }
+    /// Expiry as unix timestamp in seconds
+    pub fn get_exp(&self) -> u64 {
+        self.exp
+    }
 }
 
 #[async_trait]
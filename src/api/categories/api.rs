@@ -0,0 +1,179 @@
+use crate::api::{
+    auth::Claims,
+    categories::service::validate_category_name,
+    global::{get_default_err, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    pub name: String,
+    pub sort: i32,
+    pub household_id: Option<i32>,
+    pub ingredients: i64,
+}
+
+/// Personal categories and categories of the user's households, in the order of the walk through
+/// the store
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let categories = sqlx::query_as!(
+        GetAllRes,
+        r#"
+            SELECT c.id, c.name, c.sort, c.household_id, count(i.id) AS "ingredients!"
+            FROM category AS c
+            LEFT OUTER JOIN ingredient AS i ON c.id = i.category_id
+            WHERE has_access(c.user_id, c.household_id, $1)
+            GROUP BY c.id
+            ORDER BY c.household_id NULLS FIRST, c.sort, c.name
+        "#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting categories"))?;
+
+    Ok((StatusCode::OK, Json(categories)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: String,
+    /// Position in the walk through the store, defaults to after the last category
+    pub sort: Option<i32>,
+    /// Creates the category in a household instead of the personal library
+    pub household_id: Option<i32>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating category");
+
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    validate_category_name(
+        &payload.name,
+        None,
+        claims.get_sub(),
+        payload.household_id,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO category ( name, user_id, household_id, sort )
+            SELECT $1, $2, $3, COALESCE($4, max(sort) + 1, 1) FROM category
+            WHERE in_library(user_id, household_id, $2, $3)
+        "#,
+        payload.name,
+        claims.get_sub(),
+        payload.household_id,
+        payload.sort
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: Option<String>,
+    pub sort: Option<i32>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating category");
+
+    let category = sqlx::query!(
+        r#"
+            SELECT household_id FROM category
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    if let Some(ref name) = payload.name {
+        validate_category_name(
+            name,
+            Some(id),
+            claims.get_sub(),
+            category.household_id,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"UPDATE category SET name = COALESCE($1, name), sort = COALESCE($2, sort) WHERE id = $3"#,
+        payload.name,
+        payload.sort,
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM category
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting category"))?
+    .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
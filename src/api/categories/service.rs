@@ -0,0 +1,61 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Category names have to be unique per library
+pub async fn validate_category_name(
+    name: &str,
+    category_id: Option<i32>,
+    user_id: Uuid,
+    household_id: Option<i32>,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let existing = sqlx::query!(
+        r#"
+            SELECT id FROM category
+            WHERE lower(name) = lower($1) AND in_library(user_id, household_id, $2, $3)
+            AND id IS DISTINCT FROM $4
+        "#,
+        name,
+        user_id,
+        household_id,
+        category_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    if existing.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Category already exists".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Only categories in the library of the ingredient can be assigned, so everyone seeing the
+/// ingredient sees its category too
+pub async fn validate_category_id(
+    category_id: i32,
+    user_id: Uuid,
+    household_id: Option<i32>,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            SELECT id FROM category
+            WHERE id = $1 AND in_library(user_id, household_id, $2, $3)
+        "#,
+        category_id,
+        user_id,
+        household_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))
+    .map(|_| ())
+}
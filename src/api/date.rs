@@ -0,0 +1,49 @@
+use once_cell::sync::Lazy;
+use serde::{de, Deserialize, Deserializer, Serializer};
+use time::{format_description::FormatItem, Date};
+
+/// Dates are exchanged as `YYYY-MM-DD`
+static FORMAT: Lazy<Vec<FormatItem<'static>>> =
+    Lazy::new(|| time::format_description::parse("[year]-[month]-[day]").unwrap());
+
+pub fn serialize<S>(value: &Date, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let formatted = value.format(&FORMAT).map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&formatted)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Date, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    Date::parse(&value, &FORMAT)
+        .map_err(|_| de::Error::custom(format!("invalid date \"{}\", expected YYYY-MM-DD", value)))
+}
+
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::Date;
+
+    pub fn serialize<S>(value: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => super::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super")] Date);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(date)| date))
+    }
+}
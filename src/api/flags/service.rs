@@ -0,0 +1,153 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use std::str::FromStr;
+
+/// Allergens an ingredient contains
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Allergen {
+    Gluten,
+    Lactose,
+    Eggs,
+    Nuts,
+    Peanuts,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+}
+
+impl Allergen {
+    const ALL: [Allergen; 9] = [
+        Allergen::Gluten,
+        Allergen::Lactose,
+        Allergen::Eggs,
+        Allergen::Nuts,
+        Allergen::Peanuts,
+        Allergen::Soy,
+        Allergen::Fish,
+        Allergen::Shellfish,
+        Allergen::Sesame,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Allergen::Gluten => "gluten",
+            Allergen::Lactose => "lactose",
+            Allergen::Eggs => "eggs",
+            Allergen::Nuts => "nuts",
+            Allergen::Peanuts => "peanuts",
+            Allergen::Soy => "soy",
+            Allergen::Fish => "fish",
+            Allergen::Shellfish => "shellfish",
+            Allergen::Sesame => "sesame",
+        }
+    }
+}
+
+/// Diets an ingredient is suitable for
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Diet {
+    Vegan,
+    Vegetarian,
+}
+
+impl Diet {
+    const ALL: [Diet; 2] = [Diet::Vegan, Diet::Vegetarian];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Diet::Vegan => "vegan",
+            Diet::Vegetarian => "vegetarian",
+        }
+    }
+}
+
+/// Something a recipe has to be safe for: free of an allergen or suitable for a diet
+#[derive(Clone, Copy)]
+pub enum Restriction {
+    Allergen(Allergen),
+    Diet(Diet),
+}
+
+impl FromStr for Restriction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Allergen::ALL
+            .into_iter()
+            .find(|allergen| allergen.as_str() == value)
+            .map(Restriction::Allergen)
+            .or_else(|| {
+                Diet::ALL
+                    .into_iter()
+                    .find(|diet| diet.as_str() == value)
+                    .map(Restriction::Diet)
+            })
+            .ok_or_else(|| format!("unknown restriction \"{}\"", value))
+    }
+}
+
+/// Allergens to avoid and diets to follow
+pub fn split_restrictions(restrictions: &[Restriction]) -> (Vec<String>, Vec<String>) {
+    let mut allergens = Vec::new();
+    let mut diets = Vec::new();
+    for restriction in restrictions.iter() {
+        match restriction {
+            Restriction::Allergen(allergen) => allergens.push(allergen.as_str().to_string()),
+            Restriction::Diet(diet) => diets.push(diet.as_str().to_string()),
+        }
+    }
+    (allergens, diets)
+}
+
+/// Sorted and without duplicates, as stored on ingredients
+pub fn allergen_names(allergens: &[Allergen]) -> Vec<String> {
+    names(allergens.iter().map(Allergen::as_str))
+}
+
+/// Sorted and without duplicates, as stored on ingredients. Vegan ingredients are also
+/// vegetarian, so recipes mixing both are still vegetarian.
+pub fn diet_names(diets: &[Diet]) -> Vec<String> {
+    names(diets.iter().flat_map(|diet| match diet {
+        Diet::Vegan => vec![Diet::Vegan.as_str(), Diet::Vegetarian.as_str()],
+        Diet::Vegetarian => vec![Diet::Vegetarian.as_str()],
+    }))
+}
+
+fn names<'a>(flags: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut names: Vec<String> = flags.map(str::to_string).collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Ingredient with allergens that was added to a shopping list
+#[derive(Serialize)]
+pub struct AllergenWarning {
+    pub ingredient_id: i32,
+    pub name: String,
+    pub allergens: Vec<String>,
+}
+
+pub async fn get_allergen_warnings(
+    ingredient_ids: &[i32],
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<AllergenWarning>, (StatusCode, String)> {
+    sqlx::query_as!(
+        AllergenWarning,
+        r#"
+            SELECT id AS ingredient_id, name, allergens AS "allergens: Vec<String>"
+            FROM ingredient
+            WHERE id = ANY($1) AND cardinality(allergens) > 0
+            ORDER BY name
+        "#,
+        ingredient_ids
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
+}
@@ -1,11 +1,11 @@
 use axum::{
     async_trait, body,
-    extract::{FromRequest, RequestParts},
+    extract::{FromRequest, Query, RequestParts},
     http::StatusCode,
     response::{IntoResponse, Response},
     BoxError, Json,
 };
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use validator::{Validate, ValidationErrors};
@@ -14,6 +14,31 @@ pub fn get_default_err(message: &str) -> (StatusCode, String) {
     (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
 }
 
+/// Deserializes a comma separated query parameter like `ids=1,2,3`
+pub fn comma_separated<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Tells a missing field apart from `null`, use with `#[serde(default)]`.
+/// Missing is `None`, `null` is `Some(None)`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 pub struct ValidatedJson<T>(pub T);
 
 #[async_trait]
@@ -35,9 +60,200 @@ where
     }
 }
 
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PaginationQuery {
+    limit: Option<i64>,
+    cursor: Option<String>,
+    sort: Option<String>,
+    order: Option<SortOrder>,
+}
+
+/// Position after the last item of a page, handed to clients as an opaque string
+#[derive(Deserialize, Serialize)]
+struct Cursor {
+    sort: String,
+    order: SortOrder,
+    key: String,
+    id: i32,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        base64::encode_config(
+            serde_json::to_string(self).unwrap_or_default(),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    fn decode(value: &str) -> Option<Cursor> {
+        let decoded = base64::decode_config(value, base64::URL_SAFE_NO_PAD).ok()?;
+        serde_json::from_slice(&decoded).ok()
+    }
+}
+
+/// Keyset pagination parameters shared by all list endpoints: `limit`, `cursor`, `sort` and `order`
+pub struct Pagination {
+    limit: i64,
+    sort: Option<String>,
+    order: Option<SortOrder>,
+    cursor: Option<Cursor>,
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for Pagination
+where
+    B: Send,
+{
+    type Rejection = ServerError;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request(req)
+            .await
+            .map_err(|e| ServerError::InvalidQuery(e.to_string()))?;
+
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(ServerError::InvalidQuery(format!(
+                "Limit has to be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        let cursor = match query.cursor {
+            Some(ref cursor) => Some(
+                Cursor::decode(cursor)
+                    .ok_or_else(|| ServerError::InvalidQuery("Invalid cursor".to_string()))?,
+            ),
+            None => None,
+        };
+
+        Ok(Pagination {
+            limit,
+            sort: query.sort,
+            order: query.order,
+            cursor,
+        })
+    }
+}
+
+impl Pagination {
+    /// Requested sort field, has to be one of `fields`. The first field is the default.
+    pub fn sort<'a>(&self, fields: &[&'a str]) -> Result<&'a str, (StatusCode, String)> {
+        let sort = match self.sort {
+            Some(ref sort) => fields.iter().find(|field| *field == sort).ok_or((
+                StatusCode::BAD_REQUEST,
+                format!("Sort has to be one of {}", fields.join(", ")),
+            ))?,
+            None => fields.first().ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "No sort fields defined".to_string(),
+            ))?,
+        };
+
+        // A cursor is only valid for the sorting it was created with
+        if let Some(ref cursor) = self.cursor {
+            if cursor.sort != *sort || cursor.order != self.order() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Cursor doesn't match the sorting".to_string(),
+                ));
+            }
+        }
+
+        Ok(sort)
+    }
+
+    pub fn order(&self) -> SortOrder {
+        self.order.unwrap_or(SortOrder::Asc)
+    }
+
+    /// Sort key of the last item of the previous page
+    pub fn cursor_key(&self) -> Option<String> {
+        self.cursor.as_ref().map(|cursor| cursor.key.clone())
+    }
+
+    /// Id of the last item of the previous page
+    pub fn cursor_id(&self) -> Option<i32> {
+        self.cursor.as_ref().map(|cursor| cursor.id)
+    }
+
+    /// One more than the page size, to know if there's a next page
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// Builds a page from rows fetched with `fetch_limit`, `key` returns the sort key and id of a row
+    pub fn page<T, F>(&self, sort: &str, mut rows: Vec<T>, key: F) -> Page<T>
+    where
+        F: Fn(&T) -> (String, i32),
+    {
+        let has_next = rows.len() as i64 > self.limit;
+        rows.truncate(self.limit as usize);
+
+        let next_cursor = match rows.last() {
+            Some(last) if has_next => {
+                let (key, id) = key(last);
+                Some(
+                    Cursor {
+                        sort: sort.to_string(),
+                        order: self.order(),
+                        key,
+                        id,
+                    }
+                    .encode(),
+                )
+            }
+            _ => None,
+        };
+
+        Page {
+            items: rows,
+            next_cursor,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U, F>(self, f: F) -> Page<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
 pub enum ServerError {
     ValidationError(validator::ValidationErrors),
     AxumJsonRejection(axum::extract::rejection::JsonRejection),
+    InvalidQuery(String),
 }
 
 impl IntoResponse for ServerError {
@@ -53,6 +269,9 @@ impl IntoResponse for ServerError {
                 Json(json!({ "error": e.to_string() })),
             )
                 .into_response(),
+            ServerError::InvalidQuery(e) => {
+                (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response()
+            }
         }
     }
 }
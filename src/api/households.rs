@@ -0,0 +1,12 @@
+pub mod api;
+pub mod member;
+pub use api::create;
+pub use api::delete;
+pub use api::get_all;
+pub use api::import;
+pub use api::update;
+pub use member::add_member;
+pub use member::delete_member;
+pub use member::get_members;
+pub use member::update_member;
+pub mod service;
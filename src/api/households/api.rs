@@ -0,0 +1,328 @@
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    households::service::{release_ingredients, validate_household_id, HouseholdRole},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    id: i32,
+    name: String,
+    role: String,
+    members: i64,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let households = sqlx::query_as!(
+        GetAllRes,
+        r#"
+            SELECT h.id, h.name, hm.role, count(hm2.id) AS "members!"
+            FROM household AS h
+            JOIN household_member AS hm ON h.id = hm.household_id AND hm.user_id = $1
+            JOIN household_member AS hm2 ON h.id = hm2.household_id
+            GROUP BY h.id, hm.role
+            ORDER BY h.name
+        "#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting households"))?;
+
+    Ok((StatusCode::OK, Json(households)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: String,
+}
+
+/// Creates a household with the user as its admin
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating household");
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let household = sqlx::query!(
+        r#"INSERT INTO household ( name ) VALUES ( $1 ) RETURNING id"#,
+        payload.name
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO household_member ( household_id, user_id, role )
+            VALUES ( $1, $2, $3 )
+        "#,
+        household.id,
+        claims.get_sub(),
+        HouseholdRole::Admin.as_str()
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating household");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Admin,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"UPDATE household SET name = $1 WHERE id = $2"#,
+        payload.name,
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Deletes a household, its recipes, ingredients and shopping lists go back to their creators
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed deleting household");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Admin,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    release_ingredients(id, default_err.clone(), &mut tx).await?;
+
+    // Recipes, shopping lists and templates go back to their creators on delete
+    sqlx::query!(r#"DELETE FROM household WHERE id = $1"#, id)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ImportReq {
+    /// Moves all personal data
+    #[serde(default)]
+    all: bool,
+    /// Ingredients of the recipes are moved along with them
+    #[serde(default)]
+    recipes: Vec<i32>,
+    #[serde(default)]
+    ingredients: Vec<i32>,
+    #[serde(default)]
+    shopping: Vec<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ImportRes {
+    recipes: u64,
+    ingredients: u64,
+    shopping: u64,
+}
+
+/// Moves personal recipes, ingredients and shopping lists of the user into a household
+#[axum_macros::debug_handler]
+pub async fn import(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<ImportReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<ImportRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed moving data into household");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Member,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let recipes: Vec<i32> = sqlx::query!(
+        r#"
+            UPDATE recipe SET household_id = $1
+            WHERE user_id = $2 AND household_id IS NULL AND ($3 OR id = ANY($4))
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub(),
+        payload.all,
+        &payload.recipes
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .into_iter()
+    .map(|recipe| recipe.id)
+    .collect();
+
+    // Moved ingredients are sorted after the ingredients of the household
+    let ingredients: Vec<i32> = sqlx::query!(
+        r#"
+            WITH moved AS (
+                SELECT id, row_number() OVER (ORDER BY sort, id)::integer AS position
+                FROM ingredient
+                WHERE user_id = $2 AND household_id IS NULL AND (
+                    $3 OR id = ANY($4)
+                    OR id IN (SELECT ingredient_id FROM recipe_quantity WHERE recipe_id = ANY($5))
+                )
+            )
+            UPDATE ingredient
+            SET household_id = $1,
+            sort = (SELECT COALESCE(max(sort), 0) FROM ingredient WHERE household_id = $1) + moved.position
+            FROM moved
+            WHERE ingredient.id = moved.id
+            RETURNING ingredient.id
+        "#,
+        id,
+        claims.get_sub(),
+        payload.all,
+        &payload.ingredients,
+        &recipes
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .into_iter()
+    .map(|ingredient| ingredient.id)
+    .collect();
+
+    // Personal categories of the moved ingredients are replaced by the household category of the
+    // same name, missing ones are created after the categories of the household
+    sqlx::query!(
+        r#"
+            INSERT INTO category ( name, user_id, household_id, sort )
+            SELECT c.name, $2, $1, (
+                SELECT COALESCE(max(sort), 0) FROM category WHERE household_id = $1
+            ) + row_number() OVER (ORDER BY c.sort, c.id)::integer
+            FROM category AS c
+            WHERE c.id IN (SELECT category_id FROM ingredient WHERE id = ANY($3))
+            AND NOT EXISTS (
+                SELECT 1 FROM category AS h
+                WHERE h.household_id = $1 AND lower(h.name) = lower(c.name)
+            )
+        "#,
+        id,
+        claims.get_sub(),
+        &ingredients
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            UPDATE ingredient AS i SET category_id = (
+                SELECT h.id FROM category AS h
+                JOIN category AS c ON lower(h.name) = lower(c.name)
+                WHERE c.id = i.category_id AND h.household_id = $1
+            )
+            WHERE i.id = ANY($2) AND i.category_id IS NOT NULL
+        "#,
+        id,
+        &ingredients
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // Close the gaps in the personal sorting
+    sqlx::query!(
+        r#"
+            WITH ordered AS (
+                SELECT id, row_number() OVER (ORDER BY sort, id)::integer AS sort
+                FROM ingredient
+                WHERE user_id = $1 AND household_id IS NULL
+            )
+            UPDATE ingredient SET sort = ordered.sort
+            FROM ordered
+            WHERE ingredient.id = ordered.id
+        "#,
+        claims.get_sub()
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let shopping = sqlx::query!(
+        r#"
+            UPDATE shopping SET household_id = $1
+            WHERE user_id = $2 AND household_id IS NULL AND ($3 OR id = ANY($4))
+        "#,
+        id,
+        claims.get_sub(),
+        payload.all,
+        &payload.shopping
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .rows_affected();
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ImportRes {
+            recipes: recipes.len() as u64,
+            ingredients: ingredients.len() as u64,
+            shopping,
+        }),
+    ))
+}
@@ -0,0 +1,199 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    households::service::{validate_household_id, validate_other_admin, HouseholdRole},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct Member {
+    user_id: Uuid,
+    username: String,
+    alias: String,
+    role: HouseholdRole,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_members(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<Member>>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting household members");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Member,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let members = sqlx::query!(
+        r#"
+            SELECT u.id, u.username, u.alias, hm.role
+            FROM household_member AS hm
+            JOIN "user" AS u ON hm.user_id = u.id
+            WHERE hm.household_id = $1
+            ORDER BY u.username
+        "#,
+        id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let members = members
+        .into_iter()
+        .map(|record| {
+            Ok(Member {
+                user_id: record.id,
+                username: record.username,
+                alias: record.alias,
+                role: HouseholdRole::from_str(&record.role).map_err(|_| default_err.clone())?,
+            })
+        })
+        .collect::<Result<_, (StatusCode, String)>>()?;
+
+    Ok((StatusCode::OK, Json(members)))
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberReq {
+    username: String,
+    role: HouseholdRole,
+}
+
+/// Invites a user by username
+#[axum_macros::debug_handler]
+pub async fn add_member(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<AddMemberReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed adding household member");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Admin,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let user = sqlx::query!(
+        r#"SELECT id FROM "user" WHERE username = $1"#,
+        payload.username.to_lowercase()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO household_member ( household_id, user_id, role )
+            VALUES ( $1, $2, $3 )
+            ON CONFLICT DO NOTHING
+            RETURNING id
+        "#,
+        id,
+        user.id,
+        payload.role.as_str()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((
+        StatusCode::CONFLICT,
+        "User is already a member of this household".to_string(),
+    ))?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMemberReq {
+    role: HouseholdRole,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, Uuid)>,
+    Json(payload): Json<UpdateMemberReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating household member");
+
+    validate_household_id(
+        id,
+        claims.get_sub(),
+        HouseholdRole::Admin,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    if payload.role != HouseholdRole::Admin {
+        validate_other_admin(id, user_id, default_err.clone(), &pool).await?;
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE household_member SET role = $1
+            WHERE household_id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        payload.role.as_str(),
+        id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes a member, members can also remove themselves to leave a household
+#[axum_macros::debug_handler]
+pub async fn delete_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed removing household member");
+
+    let required = match user_id == claims.get_sub() {
+        true => HouseholdRole::Member,
+        false => HouseholdRole::Admin,
+    };
+    validate_household_id(id, claims.get_sub(), required, default_err.clone(), &pool).await?;
+
+    validate_other_admin(id, user_id, default_err.clone(), &pool).await?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM household_member
+            WHERE household_id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,260 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Admins manage the household and its members, members share its recipes, ingredients and
+/// shopping lists
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum HouseholdRole {
+    Member,
+    Admin,
+}
+
+impl HouseholdRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HouseholdRole::Member => "member",
+            HouseholdRole::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for HouseholdRole {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "member" => Ok(HouseholdRole::Member),
+            "admin" => Ok(HouseholdRole::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Makes sure the user has at least `role` in the household and returns the user's role
+pub async fn validate_household_id(
+    household_id: i32,
+    user_id: Uuid,
+    role: HouseholdRole,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<HouseholdRole, (StatusCode, String)> {
+    let member = sqlx::query!(
+        r#"SELECT role FROM household_member WHERE household_id = $1 AND user_id = $2"#,
+        household_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Household not found".to_string()))?;
+
+    let member_role = HouseholdRole::from_str(&member.role).map_err(|_| default_err)?;
+    if member_role < role {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only admins of the household can do this".to_string(),
+        ));
+    }
+    Ok(member_role)
+}
+
+/// Makes sure a household keeps at least one admin when `user_id` stops being one
+pub async fn validate_other_admin(
+    household_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let admins = sqlx::query!(
+        r#"
+            SELECT count(*) AS "admins!" FROM household_member
+            WHERE household_id = $1 AND role = 'admin' AND user_id <> $2
+        "#,
+        household_id,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|_| default_err)?
+    .admins;
+
+    if admins == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A household needs at least one admin".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Hands the ingredients of a household back to their creators before it's deleted. They're
+/// appended to the creators' personal libraries, and everyone still using an ingredient created by
+/// another member in a recipe, shopping list, template or pantry gets their own copy of it.
+/// Household categories go back to their creators too, merged into personal categories of the
+/// same name, so ingredients only keep categories created by the same member.
+pub async fn release_ingredients(
+    household_id: i32,
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    let foreign = sqlx::query!(
+        r#"
+            SELECT DISTINCT refs.user_id AS "user_id!", i.id AS "ingredient_id!"
+            FROM (
+                SELECT r.user_id, rq.ingredient_id FROM recipe_quantity AS rq
+                JOIN recipe AS r ON rq.recipe_id = r.id
+                UNION SELECT s.user_id, si.ingredient_id FROM shopping_ingredient AS si
+                JOIN shopping AS s ON si.shopping_id = s.id
+                UNION SELECT t.user_id, tq.ingredient_id FROM shopping_template_quantity AS tq
+                JOIN shopping_template AS t ON tq.shopping_template_id = t.id
+                UNION SELECT user_id, ingredient_id FROM pantry
+            ) AS refs
+            JOIN ingredient AS i ON refs.ingredient_id = i.id
+            WHERE i.household_id = $1 AND i.user_id <> refs.user_id
+        "#,
+        household_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    for record in foreign.into_iter() {
+        let copy = sqlx::query!(
+            r#"
+                INSERT INTO ingredient ( name, unit_id, sort, user_id, density, category_id, allergens, diets )
+                SELECT name, unit_id, (
+                    SELECT COALESCE(max(sort), 0) + 1 FROM ingredient
+                    WHERE user_id = $2 AND household_id IS NULL
+                ), $2, density, (
+                    SELECT id FROM category WHERE id = i.category_id AND user_id = $2
+                ), allergens, diets
+                FROM ingredient AS i
+                WHERE id = $1
+                RETURNING id
+            "#,
+            record.ingredient_id,
+            record.user_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO ingredient_nutrition ( ingredient_id, per, kcal, protein, fat, carbs, fibre, salt )
+                SELECT $2, per, kcal, protein, fat, carbs, fibre, salt
+                FROM ingredient_nutrition WHERE ingredient_id = $1
+            "#,
+            record.ingredient_id,
+            copy.id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        sqlx::query!(
+            r#"
+                WITH recipes AS (
+                    UPDATE recipe_quantity SET ingredient_id = $2
+                    WHERE ingredient_id = $1
+                    AND recipe_id IN (SELECT id FROM recipe WHERE user_id = $3)
+                ), lists AS (
+                    UPDATE shopping_ingredient SET ingredient_id = $2
+                    WHERE ingredient_id = $1
+                    AND shopping_id IN (SELECT id FROM shopping WHERE user_id = $3)
+                ), templates AS (
+                    UPDATE shopping_template_quantity SET ingredient_id = $2
+                    WHERE ingredient_id = $1
+                    AND shopping_template_id IN (SELECT id FROM shopping_template WHERE user_id = $3)
+                )
+                UPDATE pantry SET ingredient_id = $2 WHERE ingredient_id = $1 AND user_id = $3
+            "#,
+            record.ingredient_id,
+            copy.id,
+            record.user_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    let merged = sqlx::query!(
+        r#"
+            SELECT h.id AS household_category_id, p.id AS personal_category_id
+            FROM category AS h
+            JOIN category AS p ON p.user_id = h.user_id AND p.household_id IS NULL
+            AND lower(p.name) = lower(h.name)
+            WHERE h.household_id = $1
+        "#,
+        household_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    for record in merged.into_iter() {
+        sqlx::query!(
+            r#"UPDATE ingredient SET category_id = $2 WHERE category_id = $1"#,
+            record.household_category_id,
+            record.personal_category_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        sqlx::query!(
+            r#"DELETE FROM category WHERE id = $1"#,
+            record.household_category_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE category AS c SET household_id = NULL, sort = moved.sort
+            FROM (
+                SELECT id, row_number() OVER (PARTITION BY user_id ORDER BY sort, id) + COALESCE((
+                    SELECT max(sort) FROM category AS p
+                    WHERE p.user_id = h.user_id AND p.household_id IS NULL
+                ), 0) AS sort
+                FROM category AS h
+                WHERE household_id = $1
+            ) AS moved
+            WHERE c.id = moved.id
+        "#,
+        household_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // Appended in household order after each creator's personal ingredients
+    sqlx::query!(
+        r#"
+            UPDATE ingredient AS i SET household_id = NULL, sort = moved.sort, category_id = (
+                SELECT id FROM category WHERE id = i.category_id AND user_id = i.user_id
+            )
+            FROM (
+                SELECT id, row_number() OVER (PARTITION BY user_id ORDER BY sort) + COALESCE((
+                    SELECT max(sort) FROM ingredient AS p
+                    WHERE p.user_id = h.user_id AND p.household_id IS NULL
+                ), 0) AS sort
+                FROM ingredient AS h
+                WHERE household_id = $1
+            ) AS moved
+            WHERE i.id = moved.id
+        "#,
+        household_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(())
+}
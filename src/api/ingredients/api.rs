@@ -1,15 +1,25 @@
 use crate::api::{
     auth::Claims,
-    global::get_default_err,
-    ingredients::service::{get_last_ingredient_by_sort, update_ingredient_sort},
+    categories::service::validate_category_id,
+    flags::service::{allergen_names, diet_names, Allergen, Diet},
+    global::{get_default_err, nullable, Page, Pagination, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+    ingredients::service::{
+        get_ingredient, get_last_ingredient_by_sort, update_ingredient_sort, validate_unit_usage,
+    },
+    nutrition::service::{save_nutrition, Basis, Nutrition, NutritionFacts},
+    units::quantity::{self, validate_quantity},
+    units::service::validate_unit_id,
 };
 use axum::{
-    extract::{self, Path},
+    extract::{self, Path, Query},
     http::StatusCode,
     Extension, Json,
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use validator::Validate;
 
 #[derive(Serialize)]
 pub struct GetRes {
@@ -17,6 +27,13 @@ pub struct GetRes {
     name: String,
     unit: GetResUnit,
     sort: i32,
+    #[serde(with = "quantity::option")]
+    density: Option<Decimal>,
+    household_id: Option<i32>,
+    category_id: Option<i32>,
+    nutrition: Option<NutritionFacts>,
+    allergens: Vec<String>,
+    diets: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -25,19 +42,56 @@ pub struct GetResUnit {
     name: String,
 }
 
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    pub unit_id: Option<i32>,
+    /// Only ingredients of this household
+    pub household_id: Option<i32>,
+}
+
 #[axum_macros::debug_handler]
 pub async fn get_all(
     claims: Claims,
+    pagination: Pagination,
+    Query(query): Query<GetAllQuery>,
     Extension(pool): Extension<PgPool>,
-) -> Result<(StatusCode, Json<Vec<GetRes>>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Page<GetRes>>), (StatusCode, String)> {
+    let sort = pagination.sort(&["sort", "name"])?;
+
     let units = sqlx::query!(
         r#"
-        SELECT i.id, i.name, u.id AS unit_id, u.name AS unit_name, i.sort FROM ingredient AS i
-        INNER JOIN unit AS u ON i.unit_id = u.id
-        WHERE i.user_id = $1
-        ORDER BY i.sort
+        SELECT id AS "id!", name AS "name!", unit_id AS "unit_id!", unit_name AS "unit_name!",
+        sort AS "sort!", density, household_id, category_id, per AS "per?", kcal AS "kcal?",
+        protein AS "protein?", fat AS "fat?", carbs AS "carbs?", fibre AS "fibre?", salt AS "salt?",
+        allergens AS "allergens!: Vec<String>", diets AS "diets!: Vec<String>", sort_key AS "sort_key!"
+        FROM (
+            SELECT i.id, i.name, u.id AS unit_id, u.name AS unit_name, i.sort, i.density, i.household_id,
+            i.category_id, i.allergens, i.diets, n.per, n.kcal, n.protein, n.fat, n.carbs, n.fibre, n.salt,
+            CASE $2 WHEN 'name' THEN i.name ELSE lpad(i.sort::text, 10, '0') END AS sort_key
+            FROM ingredient AS i
+            INNER JOIN unit AS u ON i.unit_id = u.id
+            LEFT JOIN ingredient_nutrition AS n ON i.id = n.ingredient_id
+            WHERE has_access(i.user_id, i.household_id, $1) AND ($3::integer IS NULL OR i.unit_id = $3)
+            AND ($8::integer IS NULL OR i.household_id = $8)
+        ) AS ingredients
+        WHERE $5::text IS NULL OR CASE
+            WHEN $4 = 'desc' THEN (sort_key, id) < ($5, $6)
+            ELSE (sort_key, id) > ($5, $6)
+        END
+        ORDER BY
+            CASE WHEN $4 = 'desc' THEN sort_key END DESC,
+            CASE WHEN $4 = 'desc' THEN id END DESC,
+            sort_key, id
+        LIMIT $7
         "#,
-        claims.get_sub()
+        claims.get_sub(),
+        sort,
+        query.unit_id,
+        pagination.order().as_str(),
+        pagination.cursor_key(),
+        pagination.cursor_id(),
+        pagination.fetch_limit(),
+        query.household_id
     )
     .fetch_all(&pool)
     .await
@@ -48,8 +102,8 @@ pub async fn get_all(
         )
     })?;
 
-    let units: Vec<GetRes> = units
-        .into_iter()
+    let units = pagination
+        .page(sort, units, |record| (record.sort_key.clone(), record.id))
         .map(|record| GetRes {
             id: record.id,
             name: record.name,
@@ -58,29 +112,103 @@ pub async fn get_all(
                 name: record.unit_name,
             },
             sort: record.sort,
-        })
-        .collect();
+            density: record.density,
+            household_id: record.household_id,
+            category_id: record.category_id,
+            nutrition: record
+                .per
+                .as_deref()
+                .and_then(Basis::parse)
+                .map(|per| NutritionFacts {
+                    per,
+                    nutrition: Nutrition {
+                        kcal: record.kcal.unwrap_or_default(),
+                        protein: record.protein.unwrap_or_default(),
+                        fat: record.fat.unwrap_or_default(),
+                        carbs: record.carbs.unwrap_or_default(),
+                        fibre: record.fibre.unwrap_or_default(),
+                        salt: record.salt.unwrap_or_default(),
+                    },
+                }),
+            allergens: record.allergens,
+            diets: record.diets,
+        });
 
     Ok((StatusCode::OK, Json(units)))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct CreateReq {
     pub name: String,
     pub unit_id: i32,
     pub sort: Option<i32>,
+    /// Grams per millilitre
+    #[serde(default, with = "quantity::option")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Density has to be greater than 0 and less than 100000000"
+    ))]
+    pub density: Option<Decimal>,
+    /// Creates the ingredient in a household instead of the personal library
+    pub household_id: Option<i32>,
+    /// Aisle of the ingredient, shopping lists are grouped by it
+    pub category_id: Option<i32>,
+    #[validate]
+    pub nutrition: Option<NutritionFacts>,
+    /// Allergens the ingredient contains
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+    /// Diets the ingredient is suitable for
+    #[serde(default)]
+    pub diets: Vec<Diet>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn create(
     claims: Claims,
-    extract::Json(payload): extract::Json<CreateReq>,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed creating ingredient");
 
-    // Lat ingredient
-    let max = get_last_ingredient_by_sort(claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_unit_id(
+        payload.unit_id,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    if let Some(category_id) = payload.category_id {
+        validate_category_id(
+            category_id,
+            claims.get_sub(),
+            payload.household_id,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    // Last ingredient
+    let max = get_last_ingredient_by_sort(
+        claims.get_sub(),
+        payload.household_id,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
     let sort = match payload.sort {
         Some(sort) => {
@@ -106,25 +234,43 @@ pub async fn create(
     };
 
     let ingredients_after = sqlx::query!(
-        r#"SELECT id, sort FROM ingredient WHERE sort >= $1 AND user_id = $2 ORDER BY sort"#,
+        r#"
+            SELECT id, sort FROM ingredient
+            WHERE sort >= $1 AND in_library(user_id, household_id, $2, $3)
+            ORDER BY sort
+        "#,
         sort,
-        claims.get_sub()
+        claims.get_sub(),
+        payload.household_id
     )
     .fetch_all(&pool)
     .await
     .map_err(|_| default_err.clone())?;
 
-    sqlx::query!(
-        r#"INSERT INTO ingredient ( name, unit_id, sort, user_id ) VALUES ( $1, $2, $3, $4 )"#,
+    let ingredient = sqlx::query!(
+        r#"
+            INSERT INTO ingredient ( name, unit_id, sort, user_id, density, household_id, category_id, allergens, diets )
+            VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9 )
+            RETURNING id
+        "#,
         payload.name,
         payload.unit_id,
         sort,
-        claims.get_sub()
+        claims.get_sub(),
+        payload.density,
+        payload.household_id,
+        payload.category_id,
+        &allergen_names(&payload.allergens),
+        &diet_names(&payload.diets)
     )
-    .execute(&pool)
+    .fetch_one(&pool)
     .await
     .map_err(|_| default_err.clone())?;
 
+    if let Some(ref nutrition) = payload.nutrition {
+        save_nutrition(ingredient.id, Some(nutrition), default_err.clone(), &pool).await?;
+    }
+
     for ingredient in ingredients_after.into_iter() {
         update_ingredient_sort(
             ingredient.id,
@@ -138,34 +284,82 @@ pub async fn create(
     Ok(StatusCode::CREATED)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateReq {
     name: Option<String>,
     unit_id: Option<i32>,
+    /// Grams per millilitre, `null` removes it
+    #[serde(default, deserialize_with = "quantity::option::nullable")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Density has to be greater than 0 and less than 100000000"
+    ))]
+    density: Option<Option<Decimal>>,
+    /// `null` removes the ingredient from its category
+    #[serde(default, deserialize_with = "nullable")]
+    category_id: Option<Option<i32>>,
+    /// `null` removes the nutrition facts
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate]
+    nutrition: Option<Option<NutritionFacts>>,
+    /// Replaces the allergens of the ingredient
+    allergens: Option<Vec<Allergen>>,
+    /// Replaces the diets of the ingredient
+    diets: Option<Vec<Diet>>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn update(
     claims: Claims,
     Path(id): Path<i32>,
-    extract::Json(payload): extract::Json<UpdateReq>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed updating ingredient");
 
-    sqlx::query!(
-        r#"SELECT id FROM ingredient WHERE id = $1 AND user_id = $2"#,
-        id,
-        claims.get_sub()
+    let ingredient = get_ingredient(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    if let Some(unit_id) = payload.unit_id {
+        validate_unit_id(unit_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    if let Some(Some(category_id)) = payload.category_id {
+        validate_category_id(
+            category_id,
+            claims.get_sub(),
+            ingredient.household_id,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    // Locked so no quantities get added while the unit changes
+    let current = sqlx::query!(
+        r#"SELECT unit_id, density FROM ingredient WHERE id = $1 FOR UPDATE"#,
+        id
     )
-    .fetch_optional(&pool)
+    .fetch_one(&mut tx)
     .await
-    .map_err(|_| default_err.clone())?
-    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))?;
+    .map_err(|_| default_err.clone())?;
+
+    // Existing quantities have to stay convertible with the new unit and density
+    if payload.unit_id.is_some() || payload.density.is_some() {
+        validate_unit_usage(
+            id,
+            payload.unit_id.unwrap_or(current.unit_id),
+            payload.density.unwrap_or(current.density),
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+    }
 
     if let Some(name) = payload.name {
         sqlx::query!(r#"UPDATE ingredient SET name = $1 WHERE id = $2"#, name, id,)
-            .execute(&pool)
+            .execute(&mut tx)
             .await
             .map_err(|_| default_err.clone())?;
     }
@@ -176,11 +370,61 @@ pub async fn update(
             unit_id,
             id,
         )
-        .execute(&pool)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(density) = payload.density {
+        sqlx::query!(
+            r#"UPDATE ingredient SET density = $1 WHERE id = $2"#,
+            density,
+            id,
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(category_id) = payload.category_id {
+        sqlx::query!(
+            r#"UPDATE ingredient SET category_id = $1 WHERE id = $2"#,
+            category_id,
+            id,
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(allergens) = payload.allergens {
+        sqlx::query!(
+            r#"UPDATE ingredient SET allergens = $1 WHERE id = $2"#,
+            &allergen_names(&allergens),
+            id,
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(diets) = payload.diets {
+        sqlx::query!(
+            r#"UPDATE ingredient SET diets = $1 WHERE id = $2"#,
+            &diet_names(&diets),
+            id,
+        )
+        .execute(&mut tx)
         .await
-        .map_err(|_| default_err)?;
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(nutrition) = payload.nutrition {
+        save_nutrition(id, nutrition.as_ref(), default_err.clone(), &mut tx).await?;
     }
 
+    tx.commit().await.map_err(|_| default_err)?;
+
     Ok(StatusCode::OK)
 }
 
@@ -198,20 +442,19 @@ pub async fn sort(
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed sorting ingredient");
 
-    // Last ingredient
-    let max = get_last_ingredient_by_sort(claims.get_sub(), default_err.clone(), &pool).await?;
+    let ingredient = get_ingredient(id, claims.get_sub(), default_err.clone(), &pool).await?;
 
-    // Old position of ingredient
-    let old_sort = sqlx::query!(
-        r#"SELECT sort FROM ingredient WHERE id = $1 AND user_id = $2 ORDER BY sort DESC LIMIT 1"#,
-        id,
+    // Last ingredient
+    let max = get_last_ingredient_by_sort(
         claims.get_sub(),
+        ingredient.household_id,
+        default_err.clone(),
+        &pool,
     )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| default_err.clone())?
-    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))?
-    .sort;
+    .await?;
+
+    // Old position of ingredient
+    let old_sort = ingredient.sort;
 
     if payload.new_sort < 1 {
         payload.new_sort = 1;
@@ -232,10 +475,15 @@ pub async fn sort(
 
     if payload.new_sort < old_sort {
         let add_sort = sqlx::query!(
-            r#"SELECT id, sort FROM ingredient WHERE sort >= $1 AND sort < $2 AND user_id = $3 ORDER BY sort"#,
+            r#"
+                SELECT id, sort FROM ingredient
+                WHERE sort >= $1 AND sort < $2 AND in_library(user_id, household_id, $3, $4)
+                ORDER BY sort
+            "#,
             payload.new_sort,
             old_sort,
-            claims.get_sub()
+            claims.get_sub(),
+            ingredient.household_id
         )
         .fetch_all(&pool)
         .await
@@ -254,10 +502,15 @@ pub async fn sort(
 
     if payload.new_sort > old_sort {
         let subtract_sort = sqlx::query!(
-            r#"SELECT id, sort FROM ingredient WHERE sort > $1 AND sort <= $2 AND user_id = $3 ORDER BY sort"#,
+            r#"
+                SELECT id, sort FROM ingredient
+                WHERE sort > $1 AND sort <= $2 AND in_library(user_id, household_id, $3, $4)
+                ORDER BY sort
+            "#,
             old_sort,
             payload.new_sort,
-            claims.get_sub()
+            claims.get_sub(),
+            ingredient.household_id
         )
         .fetch_all(&pool)
         .await
@@ -287,15 +540,7 @@ pub async fn delete(
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed deleting ingredient");
 
-    let to_delete = sqlx::query!(
-        r#"SELECT id, sort FROM ingredient WHERE id = $1 AND user_id = $2"#,
-        id,
-        claims.get_sub()
-    )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| default_err.clone())?
-    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))?;
+    let to_delete = get_ingredient(id, claims.get_sub(), default_err.clone(), &pool).await?;
 
     sqlx::query!(r#"DELETE FROM ingredient WHERE id = $1"#, id)
         .execute(&pool)
@@ -303,9 +548,14 @@ pub async fn delete(
         .map_err(|_| default_err.clone())?;
 
     let ingredients_after = sqlx::query!(
-        r#"SELECT id, sort FROM ingredient WHERE sort > $1 AND user_id = $2 ORDER BY sort"#,
+        r#"
+            SELECT id, sort FROM ingredient
+            WHERE sort > $1 AND in_library(user_id, household_id, $2, $3)
+            ORDER BY sort
+        "#,
         to_delete.sort,
-        claims.get_sub()
+        claims.get_sub(),
+        to_delete.household_id
     )
     .fetch_all(&pool)
     .await
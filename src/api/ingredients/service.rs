@@ -1,5 +1,7 @@
+use crate::api::units::service::{check_unit, Unit};
 use axum::http::StatusCode;
-use sqlx::PgPool;
+use rust_decimal::Decimal;
+use sqlx::{PgExecutor, PgPool};
 use uuid::Uuid;
 
 pub async fn update_ingredient_sort(
@@ -23,17 +25,95 @@ pub struct IngredientSort {
     pub sort: i32,
 }
 
+/// Ingredients are sorted within the personal library of a user or the library of a household
 pub async fn get_last_ingredient_by_sort(
     user_id: Uuid,
+    household_id: Option<i32>,
     default_err: (StatusCode, String),
     pool: &PgPool,
 ) -> Result<Option<IngredientSort>, (StatusCode, String)> {
     sqlx::query_as!(
         IngredientSort,
-        r#"SELECT sort FROM ingredient WHERE user_id = $1 ORDER BY sort DESC LIMIT 1"#,
-        user_id
+        r#"
+            SELECT sort FROM ingredient
+            WHERE in_library(user_id, household_id, $1, $2)
+            ORDER BY sort DESC LIMIT 1
+        "#,
+        user_id,
+        household_id
     )
     .fetch_optional(pool)
     .await
     .map_err(|_| default_err.clone())
 }
+
+pub struct IngredientOwner {
+    pub sort: i32,
+    pub household_id: Option<i32>,
+}
+
+/// Sort and household of an ingredient the user has access to
+pub async fn get_ingredient(
+    id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<IngredientOwner, (StatusCode, String)> {
+    sqlx::query_as!(
+        IngredientOwner,
+        r#"
+            SELECT sort, household_id FROM ingredient
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))
+}
+
+/// Makes sure every recipe, shopping, pantry, template and price quantity of an ingredient can
+/// still be converted to its unit with the given unit and density
+pub async fn validate_unit_usage(
+    id: i32,
+    unit_id: i32,
+    density: Option<Decimal>,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<(), (StatusCode, String)> {
+    let units = sqlx::query_as!(
+        Unit,
+        r#"
+            SELECT id, name, family, factor, user_id FROM unit
+            WHERE id = $2 OR id IN (
+                SELECT unit_id FROM recipe_quantity WHERE ingredient_id = $1
+                UNION SELECT sq.unit_id FROM shopping_quantity AS sq
+                JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+                WHERE si.ingredient_id = $1
+                UNION SELECT unit_id FROM pantry WHERE ingredient_id = $1
+                UNION SELECT unit_id FROM shopping_template_quantity WHERE ingredient_id = $1
+                UNION SELECT unit_id FROM ingredient_price WHERE ingredient_id = $1
+            )
+        "#,
+        id,
+        unit_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)?;
+
+    for unit in units.iter() {
+        check_unit(unit.id, id, unit_id, density, &units).map_err(|_| {
+            (
+                StatusCode::CONFLICT,
+                format!(
+                    "Quantities of the ingredient in {} can't be converted to its new unit",
+                    unit.name
+                ),
+            )
+        })?;
+    }
+    Ok(())
+}
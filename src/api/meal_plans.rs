@@ -0,0 +1,8 @@
+pub mod api;
+pub use api::copy;
+pub use api::create;
+pub use api::delete;
+pub use api::get_all;
+pub use api::nutrition;
+pub use api::update;
+mod service;
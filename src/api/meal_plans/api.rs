@@ -0,0 +1,361 @@
+use crate::api::{
+    auth::Claims,
+    date,
+    global::{get_default_err, ValidatedJson},
+    meal_plans::service::{get_recipe_servings, Slot},
+    nutrition::service::{get_nutrition, nutrition_of, Nutrition},
+    units::service::{find_unit, get_units},
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::{Date, Duration};
+use validator::Validate;
+
+/// Longest date range that can be requested at once
+pub const MAX_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    #[serde(with = "date")]
+    from: Date,
+    #[serde(with = "date")]
+    to: Date,
+}
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    id: i32,
+    #[serde(with = "date")]
+    date: Date,
+    slot: String,
+    servings: i32,
+    recipe: Recipe,
+}
+
+#[derive(Serialize)]
+pub struct Recipe {
+    id: i32,
+    name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Query(query): Query<GetAllQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    if query.to < query.from {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "End date can't be before start date".to_string(),
+        ));
+    }
+    if (query.to - query.from).whole_days() >= MAX_DAYS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Date range can't exceed {} days", MAX_DAYS),
+        ));
+    }
+
+    let meals = sqlx::query!(
+        r#"
+            SELECT mp.id, mp.date, mp.slot, mp.servings, r.id AS recipe_id, r.name AS recipe_name
+            FROM meal_plan AS mp
+            JOIN recipe AS r ON mp.recipe_id = r.id
+            WHERE mp.user_id = $1 AND mp.date BETWEEN $2 AND $3
+            ORDER BY mp.date,
+            array_position(ARRAY['breakfast', 'lunch', 'dinner', 'snack']::varchar[], mp.slot),
+            mp.id
+        "#,
+        claims.get_sub(),
+        query.from,
+        query.to
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting meal plan"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(
+            meals
+                .into_iter()
+                .map(|record| GetAllRes {
+                    id: record.id,
+                    date: record.date,
+                    slot: record.slot,
+                    servings: record.servings,
+                    recipe: Recipe {
+                        id: record.recipe_id,
+                        name: record.recipe_name,
+                    },
+                })
+                .collect(),
+        ),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    recipe_id: i32,
+    #[serde(with = "date")]
+    date: Date,
+    slot: Slot,
+    /// Defaults to the servings of the recipe
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    servings: Option<i32>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating meal");
+
+    let recipe_servings = get_recipe_servings(
+        payload.recipe_id,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO meal_plan ( user_id, recipe_id, date, slot, servings )
+            VALUES ( $1, $2, $3, $4, $5 )
+        "#,
+        claims.get_sub(),
+        payload.recipe_id,
+        payload.date,
+        payload.slot.as_str(),
+        payload.servings.unwrap_or(recipe_servings)
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    recipe_id: Option<i32>,
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    date: Option<Date>,
+    slot: Option<Slot>,
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    servings: Option<i32>,
+}
+
+/// Moves a meal to another date or slot, or changes its recipe or servings
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating meal");
+
+    if let Some(recipe_id) = payload.recipe_id {
+        get_recipe_servings(recipe_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE meal_plan
+            SET recipe_id = COALESCE($1, recipe_id),
+            date = COALESCE($2, date),
+            slot = COALESCE($3, slot),
+            servings = COALESCE($4, servings)
+            WHERE id = $5 AND user_id = $6
+            RETURNING id
+        "#,
+        payload.recipe_id,
+        payload.date,
+        payload.slot.map(|slot| slot.as_str()),
+        payload.servings,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Meal not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct CopyReq {
+    #[serde(with = "date")]
+    date: Date,
+    /// Defaults to the slot of the copied meal
+    slot: Option<Slot>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn copy(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<CopyReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            INSERT INTO meal_plan ( user_id, recipe_id, date, slot, servings )
+            SELECT user_id, recipe_id, $1, COALESCE($2, slot), servings
+            FROM meal_plan
+            WHERE id = $3 AND user_id = $4
+            RETURNING id
+        "#,
+        payload.date,
+        payload.slot.map(|slot| slot.as_str()),
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed copying meal"))?
+    .ok_or((StatusCode::NOT_FOUND, "Meal not found".to_string()))?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM meal_plan
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting meal"))?
+    .ok_or((StatusCode::NOT_FOUND, "Meal not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct NutritionQuery {
+    /// First day of the week
+    #[serde(with = "date")]
+    from: Date,
+}
+
+#[derive(Serialize)]
+pub struct NutritionRes {
+    #[serde(with = "date")]
+    from: Date,
+    #[serde(with = "date")]
+    to: Date,
+    total: Nutrition,
+    days: Vec<Day>,
+    /// Ingredients without nutrition facts, they're missing from the totals
+    missing: Vec<i32>,
+}
+
+#[derive(Serialize)]
+pub struct Day {
+    #[serde(with = "date")]
+    date: Date,
+    nutrition: Nutrition,
+}
+
+/// Nutrition of all meals planned in the week starting at `from`, scaled to their servings
+#[axum_macros::debug_handler]
+pub async fn nutrition(
+    claims: Claims,
+    Query(query): Query<NutritionQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<NutritionRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting meal plan nutrition");
+
+    let to = query.from + Duration::days(6);
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT mp.date, mp.servings, r.servings AS recipe_servings, rq.ingredient_id,
+            rq.quantity, rq.unit_id, i.density
+            FROM meal_plan AS mp
+            JOIN recipe AS r ON mp.recipe_id = r.id
+            JOIN recipe_quantity AS rq ON r.id = rq.recipe_id
+            JOIN ingredient AS i ON rq.ingredient_id = i.id
+            WHERE mp.user_id = $1 AND mp.date BETWEEN $2 AND $3
+            ORDER BY mp.date
+        "#,
+        claims.get_sub(),
+        query.from,
+        to
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let ingredient_ids: Vec<i32> = quantities.iter().map(|q| q.ingredient_id).collect();
+    let facts = get_nutrition(&ingredient_ids, default_err.clone(), &pool).await?;
+    let units = get_units(claims.get_sub(), default_err, &pool).await?;
+
+    let mut total = Nutrition::default();
+    let mut days: Vec<Day> = Vec::new();
+    let mut missing = Vec::new();
+    for q in quantities.into_iter() {
+        let nutrition = find_unit(q.unit_id, &units).ok().and_then(|unit| {
+            nutrition_of(q.quantity, unit, q.ingredient_id, q.density, &facts, &units)
+        });
+        let nutrition = match nutrition {
+            Some(nutrition) => {
+                nutrition.scale(Decimal::from(q.servings) / Decimal::from(q.recipe_servings))
+            }
+            None => {
+                if !missing.contains(&q.ingredient_id) {
+                    missing.push(q.ingredient_id);
+                }
+                continue;
+            }
+        };
+        total += nutrition;
+        match days.last_mut() {
+            Some(day) if day.date == q.date => day.nutrition += nutrition,
+            _ => days.push(Day {
+                date: q.date,
+                nutrition,
+            }),
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(NutritionRes {
+            from: query.from,
+            to,
+            total: total.round(),
+            days: days
+                .into_iter()
+                .map(|day| Day {
+                    date: day.date,
+                    nutrition: day.nutrition.round(),
+                })
+                .collect(),
+            missing,
+        }),
+    ))
+}
@@ -0,0 +1,43 @@
+use axum::http::StatusCode;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Slot {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+impl Slot {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Slot::Breakfast => "breakfast",
+            Slot::Lunch => "lunch",
+            Slot::Dinner => "dinner",
+            Slot::Snack => "snack",
+        }
+    }
+}
+
+/// Servings of a recipe available to the user, used as the default servings of a meal
+pub async fn get_recipe_servings(
+    recipe_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<i32, (StatusCode, String)> {
+    sqlx::query!(
+        r#"SELECT servings FROM recipe WHERE id = $1 AND has_access(user_id, household_id, $2)"#,
+        recipe_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Recipe not found".to_string()))
+    .map(|recipe| recipe.servings)
+}
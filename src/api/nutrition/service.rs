@@ -0,0 +1,226 @@
+use crate::api::units::{
+    quantity,
+    service::{convert, Unit},
+};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use std::ops::AddAssign;
+use validator::{Validate, ValidationError};
+
+/// Decimal places nutrition values are stored with
+const SCALE: u32 = 2;
+
+/// Amount of an ingredient the nutrition facts refer to
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum Basis {
+    #[serde(rename = "100g")]
+    Grams,
+    #[serde(rename = "100ml")]
+    Millilitres,
+    #[serde(rename = "piece")]
+    Piece,
+}
+
+impl Basis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Basis::Grams => "100g",
+            Basis::Millilitres => "100ml",
+            Basis::Piece => "piece",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "100g" => Some(Basis::Grams),
+            "100ml" => Some(Basis::Millilitres),
+            "piece" => Some(Basis::Piece),
+            _ => None,
+        }
+    }
+
+    /// Global unit and amount of it the facts refer to
+    fn reference(&self) -> (&'static str, Decimal) {
+        match self {
+            Basis::Grams => ("g", Decimal::ONE_HUNDRED),
+            Basis::Millilitres => ("ml", Decimal::ONE_HUNDRED),
+            Basis::Piece => ("none", Decimal::ONE),
+        }
+    }
+}
+
+pub fn validate_nutrient(value: &Decimal) -> Result<(), ValidationError> {
+    if value.is_sign_negative() {
+        return Err(ValidationError::new("invalid_nutrient"));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Validate)]
+pub struct NutritionFacts {
+    pub per: Basis,
+    #[serde(flatten)]
+    #[validate]
+    pub nutrition: Nutrition,
+}
+
+/// Nutrition values, energy in kcal and everything else in grams
+#[derive(Deserialize, Serialize, Validate, Default, Clone, Copy)]
+pub struct Nutrition {
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Kcal can't be negative"))]
+    pub kcal: Decimal,
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Protein can't be negative"))]
+    pub protein: Decimal,
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Fat can't be negative"))]
+    pub fat: Decimal,
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Carbs can't be negative"))]
+    pub carbs: Decimal,
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Fibre can't be negative"))]
+    pub fibre: Decimal,
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_nutrient", message = "Salt can't be negative"))]
+    pub salt: Decimal,
+}
+
+impl Nutrition {
+    pub fn scale(self, factor: Decimal) -> Self {
+        Nutrition {
+            kcal: self.kcal * factor,
+            protein: self.protein * factor,
+            fat: self.fat * factor,
+            carbs: self.carbs * factor,
+            fibre: self.fibre * factor,
+            salt: self.salt * factor,
+        }
+    }
+
+    pub fn round(self) -> Self {
+        let round = |value: Decimal| value.round_dp(SCALE).normalize();
+        Nutrition {
+            kcal: round(self.kcal),
+            protein: round(self.protein),
+            fat: round(self.fat),
+            carbs: round(self.carbs),
+            fibre: round(self.fibre),
+            salt: round(self.salt),
+        }
+    }
+}
+
+impl AddAssign for Nutrition {
+    fn add_assign(&mut self, other: Self) {
+        self.kcal += other.kcal;
+        self.protein += other.protein;
+        self.fat += other.fat;
+        self.carbs += other.carbs;
+        self.fibre += other.fibre;
+        self.salt += other.salt;
+    }
+}
+
+pub struct IngredientNutrition {
+    pub ingredient_id: i32,
+    pub per: String,
+    pub kcal: Decimal,
+    pub protein: Decimal,
+    pub fat: Decimal,
+    pub carbs: Decimal,
+    pub fibre: Decimal,
+    pub salt: Decimal,
+}
+
+impl IngredientNutrition {
+    pub fn nutrition(&self) -> Nutrition {
+        Nutrition {
+            kcal: self.kcal,
+            protein: self.protein,
+            fat: self.fat,
+            carbs: self.carbs,
+            fibre: self.fibre,
+            salt: self.salt,
+        }
+    }
+}
+
+pub async fn get_nutrition(
+    ingredient_ids: &[i32],
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<IngredientNutrition>, (StatusCode, String)> {
+    sqlx::query_as!(
+        IngredientNutrition,
+        r#"
+            SELECT ingredient_id, per, kcal, protein, fat, carbs, fibre, salt
+            FROM ingredient_nutrition
+            WHERE ingredient_id = ANY($1)
+        "#,
+        ingredient_ids
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
+}
+
+/// Replaces the nutrition facts of an ingredient, `None` removes them
+pub async fn save_nutrition(
+    ingredient_id: i32,
+    facts: Option<&NutritionFacts>,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<(), (StatusCode, String)> {
+    match facts {
+        Some(facts) => sqlx::query!(
+            r#"
+                INSERT INTO ingredient_nutrition ( ingredient_id, per, kcal, protein, fat, carbs, fibre, salt )
+                VALUES ( $1, $2, $3, $4, $5, $6, $7, $8 )
+                ON CONFLICT (ingredient_id) DO UPDATE
+                SET per = $2, kcal = $3, protein = $4, fat = $5, carbs = $6, fibre = $7, salt = $8
+            "#,
+            ingredient_id,
+            facts.per.as_str(),
+            facts.nutrition.kcal,
+            facts.nutrition.protein,
+            facts.nutrition.fat,
+            facts.nutrition.carbs,
+            facts.nutrition.fibre,
+            facts.nutrition.salt
+        )
+        .execute(executor)
+        .await,
+        None => sqlx::query!(
+            r#"DELETE FROM ingredient_nutrition WHERE ingredient_id = $1"#,
+            ingredient_id
+        )
+        .execute(executor)
+        .await,
+    }
+    .map_err(|_| default_err)
+    .map(|_| ())
+}
+
+/// Nutrition of a quantity of an ingredient, `None` without nutrition facts or if the quantity
+/// can't be converted to the amount the facts refer to
+pub fn nutrition_of(
+    quantity: Decimal,
+    unit: &Unit,
+    ingredient_id: i32,
+    density: Option<Decimal>,
+    facts: &[IngredientNutrition],
+    units: &[Unit],
+) -> Option<Nutrition> {
+    let facts = facts.iter().find(|f| f.ingredient_id == ingredient_id)?;
+    let (reference, amount) = Basis::parse(&facts.per)?.reference();
+    let reference = units
+        .iter()
+        .find(|u| u.user_id.is_none() && u.name == reference)?;
+    let quantity = convert(quantity, unit, reference, density)?;
+
+    Some(facts.nutrition().scale(quantity / amount))
+}
@@ -0,0 +1,219 @@
+use crate::api::{
+    auth::Claims,
+    date,
+    global::{get_default_err, ValidatedJson},
+    pantry::service::add_pantry_quantity,
+    units::{
+        quantity::{self, validate_quantity},
+        service::{check_unit, get_units},
+    },
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::Date;
+use validator::Validate;
+
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    /// Only items expiring before this date
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    expires_before: Option<Date>,
+}
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    id: i32,
+    ingredient: Ingredient,
+    #[serde(with = "quantity")]
+    quantity: Decimal,
+    unit_id: i32,
+    unit: String,
+    #[serde(with = "date::option")]
+    expires_at: Option<Date>,
+}
+
+#[derive(Serialize)]
+pub struct Ingredient {
+    id: i32,
+    name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Query(query): Query<GetAllQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let items = sqlx::query!(
+        r#"
+            SELECT p.id, p.quantity, p.expires_at, i.id AS ingredient_id, i.name AS ingredient_name,
+            u.id AS unit_id, u.name AS unit
+            FROM pantry AS p
+            JOIN ingredient AS i ON p.ingredient_id = i.id
+            JOIN unit AS u ON p.unit_id = u.id
+            WHERE p.user_id = $1 AND ($2::date IS NULL OR p.expires_at < $2)
+            ORDER BY p.expires_at NULLS LAST, i.name, p.id
+        "#,
+        claims.get_sub(),
+        query.expires_before
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting pantry"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(
+            items
+                .into_iter()
+                .map(|record| GetAllRes {
+                    id: record.id,
+                    ingredient: Ingredient {
+                        id: record.ingredient_id,
+                        name: record.ingredient_name,
+                    },
+                    quantity: record.quantity,
+                    unit_id: record.unit_id,
+                    unit: record.unit,
+                    expires_at: record.expires_at,
+                })
+                .collect(),
+        ),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    ingredient_id: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    quantity: Decimal,
+    /// Defaults to the unit of the ingredient
+    unit_id: Option<i32>,
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    expires_at: Option<Date>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed adding to pantry");
+
+    let mut conn = pool.acquire().await.map_err(|_| default_err.clone())?;
+
+    add_pantry_quantity(
+        payload.ingredient_id,
+        payload.quantity,
+        payload.unit_id,
+        payload.expires_at,
+        claims.get_sub(),
+        default_err,
+        &mut conn,
+    )
+    .await?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[serde(default, with = "quantity::option")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    quantity: Option<Decimal>,
+    unit_id: Option<i32>,
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    expires_at: Option<Date>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating pantry item");
+
+    let item = sqlx::query!(
+        r#"
+            SELECT p.ingredient_id, i.unit_id, i.density
+            FROM pantry AS p
+            JOIN ingredient AS i ON p.ingredient_id = i.id
+            WHERE p.id = $1 AND p.user_id = $2
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Pantry item not found".to_string()))?;
+
+    if let Some(unit_id) = payload.unit_id {
+        let units = get_units(claims.get_sub(), default_err.clone(), &pool).await?;
+        check_unit(
+            unit_id,
+            item.ingredient_id,
+            item.unit_id,
+            item.density,
+            &units,
+        )?;
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE pantry
+            SET quantity = COALESCE($1, quantity),
+            unit_id = COALESCE($2, unit_id),
+            expires_at = COALESCE($3, expires_at)
+            WHERE id = $4
+        "#,
+        payload.quantity,
+        payload.unit_id,
+        payload.expires_at,
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM pantry
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting pantry item"))?
+    .ok_or((StatusCode::NOT_FOUND, "Pantry item not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
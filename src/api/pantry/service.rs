@@ -0,0 +1,126 @@
+use crate::api::units::{
+    quantity::check_bounds,
+    service::{check_unit, convert, find_unit, get_units},
+};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use time::Date;
+use uuid::Uuid;
+
+pub struct PantryStock {
+    pub ingredient_id: i32,
+    pub quantity: Decimal,
+    pub unit_id: i32,
+}
+
+/// Pantry quantities of the given ingredients in the pantries of the user and of everyone
+/// sharing a household with them
+pub async fn get_pantry_stock(
+    ingredient_ids: &[i32],
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<Vec<PantryStock>, (StatusCode, String)> {
+    sqlx::query_as!(
+        PantryStock,
+        r#"
+            SELECT ingredient_id, quantity, unit_id FROM pantry
+            WHERE user_id IN (SELECT co_members($1)) AND ingredient_id = ANY($2)
+        "#,
+        user_id,
+        ingredient_ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|_| default_err)
+}
+
+/// Adds a quantity to the pantry, merged into the item of the ingredient with the same expiry date
+pub async fn add_pantry_quantity(
+    ingredient_id: i32,
+    quantity: Decimal,
+    unit_id: Option<i32>,
+    expires_at: Option<Date>,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    let ingredient = sqlx::query!(
+        r#"
+            SELECT unit_id, density FROM ingredient
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        ingredient_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("Ingredient with id {} not found", ingredient_id),
+    ))?;
+
+    let units = get_units(user_id, default_err.clone(), &mut *conn).await?;
+    let unit_id = unit_id.unwrap_or(ingredient.unit_id);
+    check_unit(
+        unit_id,
+        ingredient_id,
+        ingredient.unit_id,
+        ingredient.density,
+        &units,
+    )?;
+
+    let existing = sqlx::query!(
+        r#"
+            SELECT id, quantity, unit_id FROM pantry
+            WHERE user_id = $1 AND ingredient_id = $2 AND expires_at IS NOT DISTINCT FROM $3
+        "#,
+        user_id,
+        ingredient_id,
+        expires_at
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    if let Some(existing) = existing {
+        // Add to the existing quantity in its unit
+        let quantity = check_bounds(
+            convert(
+                quantity,
+                find_unit(unit_id, &units)?,
+                find_unit(existing.unit_id, &units)?,
+                ingredient.density,
+            )
+            .and_then(|quantity| existing.quantity.checked_add(quantity)),
+        )?;
+
+        sqlx::query!(
+            r#"UPDATE pantry SET quantity = $1 WHERE id = $2"#,
+            quantity,
+            existing.id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err)?;
+    } else {
+        sqlx::query!(
+            r#"
+                INSERT INTO pantry ( user_id, ingredient_id, quantity, unit_id, expires_at )
+                VALUES ( $1, $2, $3, $4, $5 )
+            "#,
+            user_id,
+            ingredient_id,
+            quantity,
+            unit_id,
+            expires_at
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err)?;
+    }
+
+    Ok(())
+}
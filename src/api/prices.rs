@@ -0,0 +1,5 @@
+pub mod api;
+pub use api::create;
+pub use api::delete;
+pub use api::get_all;
+pub mod service;
@@ -0,0 +1,177 @@
+use crate::api::{
+    auth::Claims,
+    date,
+    global::{get_default_err, ValidatedJson},
+    prices::service::validate_price,
+    stores::service::validate_store_id,
+    units::{
+        quantity::{self, validate_quantity},
+        service::{check_unit, get_units},
+    },
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::Date;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    #[serde(with = "quantity")]
+    pub price: Decimal,
+    /// Quantity the price was paid for
+    #[serde(with = "quantity")]
+    pub quantity: Decimal,
+    pub unit_id: i32,
+    pub unit: String,
+    pub store_id: Option<i32>,
+    pub store: Option<String>,
+    #[serde(with = "date")]
+    pub observed_on: Date,
+}
+
+/// Price history of an ingredient, newest first
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting prices");
+
+    validate_ingredient_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    let prices = sqlx::query_as!(
+        GetAllRes,
+        r#"
+            SELECT p.id, p.price, p.quantity, u.id AS unit_id, u.name AS unit,
+            s.id AS "store_id?", s.name AS "store?", p.observed_on
+            FROM ingredient_price AS p
+            JOIN unit AS u ON p.unit_id = u.id
+            LEFT JOIN store AS s ON p.store_id = s.id
+            WHERE p.ingredient_id = $1 AND p.user_id IN (SELECT co_members($2))
+            ORDER BY p.observed_on DESC, p.id DESC
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok((StatusCode::OK, Json(prices)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[serde(with = "quantity")]
+    #[validate(custom(function = "validate_price", message = "Price can't be negative"))]
+    price: Decimal,
+    /// Quantity the price was paid for
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    quantity: Decimal,
+    /// Defaults to the unit of the ingredient
+    unit_id: Option<i32>,
+    store_id: Option<i32>,
+    /// Defaults to today
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    observed_on: Option<Date>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed adding price");
+
+    let ingredient =
+        validate_ingredient_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    let unit_id = payload.unit_id.unwrap_or(ingredient.unit_id);
+    let units = get_units(claims.get_sub(), default_err.clone(), &pool).await?;
+    check_unit(unit_id, id, ingredient.unit_id, ingredient.density, &units)?;
+
+    if let Some(store_id) = payload.store_id {
+        validate_store_id(store_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    sqlx::query!(
+        r#"
+            INSERT INTO ingredient_price ( ingredient_id, user_id, store_id, price, quantity, unit_id, observed_on )
+            VALUES ( $1, $2, $3, $4, $5, $6, COALESCE($7, current_date) )
+        "#,
+        id,
+        claims.get_sub(),
+        payload.store_id,
+        payload.price,
+        payload.quantity,
+        unit_id,
+        payload.observed_on
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Only the user who observed a price can delete it
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path((id, price_id)): Path<(i32, i32)>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM ingredient_price
+            WHERE id = $1 AND ingredient_id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        price_id,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting price"))?
+    .ok_or((StatusCode::NOT_FOUND, "Price not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+struct PricedIngredient {
+    unit_id: i32,
+    density: Option<Decimal>,
+}
+
+async fn validate_ingredient_id(
+    id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<PricedIngredient, (StatusCode, String)> {
+    sqlx::query_as!(
+        PricedIngredient,
+        r#"
+            SELECT unit_id, density FROM ingredient
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))
+}
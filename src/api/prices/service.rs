@@ -0,0 +1,79 @@
+use crate::api::units::service::{convert, Unit};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+use validator::ValidationError;
+
+/// Decimal places prices are stored with
+const SCALE: u32 = 2;
+
+pub fn validate_price(value: &Decimal) -> Result<(), ValidationError> {
+    if value.is_sign_negative() {
+        return Err(ValidationError::new("invalid_price"));
+    }
+    Ok(())
+}
+
+/// Price paid for a quantity of an ingredient
+pub struct Price {
+    pub ingredient_id: i32,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub unit_id: i32,
+}
+
+/// Latest price of every ingredient observed by the user or by anyone sharing a household with
+/// them. Prices observed at `store_id` win over newer ones from other stores.
+pub async fn get_latest_prices(
+    ingredient_ids: &[i32],
+    user_id: Uuid,
+    store_id: Option<i32>,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Price>, (StatusCode, String)> {
+    sqlx::query_as!(
+        Price,
+        r#"
+            SELECT DISTINCT ON (ingredient_id) ingredient_id, price, quantity, unit_id
+            FROM ingredient_price
+            WHERE ingredient_id = ANY($1) AND user_id IN (SELECT co_members($2))
+            ORDER BY ingredient_id, (store_id = $3) IS TRUE DESC, observed_on DESC, id DESC
+        "#,
+        ingredient_ids,
+        user_id,
+        store_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
+}
+
+/// Cost of a quantity of an ingredient, `None` without a price or if the quantity can't be
+/// converted to the unit the price was observed in
+pub fn cost(
+    quantity: Decimal,
+    unit: &Unit,
+    ingredient_id: i32,
+    density: Option<Decimal>,
+    prices: &[Price],
+    units: &[Unit],
+) -> Option<Decimal> {
+    let price = prices.iter().find(|p| p.ingredient_id == ingredient_id)?;
+    let price_unit = units.iter().find(|u| u.id == price.unit_id)?;
+    let quantity = convert(quantity, unit, price_unit, density)?;
+
+    price
+        .price
+        .checked_mul(quantity)?
+        .checked_div(price.quantity)
+        .map(|cost| cost.round_dp(SCALE).normalize())
+}
+
+/// Sum of all known costs, `None` if none is known
+pub fn total_cost(costs: impl IntoIterator<Item = Option<Decimal>>) -> Option<Decimal> {
+    costs
+        .into_iter()
+        .flatten()
+        .reduce(|total, cost| total + cost)
+}
@@ -1,7 +1,12 @@
 pub mod api;
+pub mod substitute;
+pub mod suggest;
 pub use api::create;
 pub use api::delete;
 pub use api::get;
 pub use api::get_all;
+pub use api::sort_step;
 pub use api::update;
+pub use substitute::substitute;
+pub use suggest::suggest;
 mod service;
@@ -1,37 +1,147 @@
-use crate::api::{auth::Claims, recipes::service::save_recipe_ingredients};
+use crate::api::{
+    auth::Claims,
+    flags::service::{split_restrictions, Restriction},
+    global::{comma_separated, get_default_err, Page, Pagination, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+    nutrition::service::{get_nutrition, nutrition_of, IngredientNutrition, Nutrition},
+    prices::service::{cost, get_latest_prices, total_cost, Price},
+    recipes::service::{
+        get_last_step_by_sort, get_recipe_tags, save_recipe_ingredients, save_recipe_steps,
+        save_recipe_tags, update_step_sort, validate_recipe_access, RecipeTag,
+    },
+    units::{
+        quantity::{self, validate_quantity},
+        service::{find_unit, get_units, scale_quantity, Unit},
+    },
+};
 use axum::{
-    extract::{self, Path},
+    extract::{self, Path, Query},
     http::StatusCode,
     Extension, Json,
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use time::{OffsetDateTime, PrimitiveDateTime};
+use validator::Validate;
 
 #[derive(Serialize)]
 pub struct GetAllRes {
     pub id: i32,
     pub name: String,
+    pub servings: i32,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
     pub ingredients: i64,
+    pub tags: Vec<TagDetail>,
+    pub household_id: Option<i32>,
+    /// Allergens contained in any ingredient
+    pub allergens: Vec<String>,
+    /// Diets all ingredients are suitable for
+    pub diets: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagDetail {
+    pub id: i32,
+    pub name: String,
+}
+
+fn tags_of_recipe(recipe_id: i32, tags: &[RecipeTag]) -> Vec<TagDetail> {
+    tags.iter()
+        .filter(|t| t.recipe_id == recipe_id)
+        .map(|t| TagDetail {
+            id: t.id,
+            name: t.name.clone(),
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    /// Only recipes updated at or after this point in time
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub updated_since: Option<OffsetDateTime>,
+    /// Only recipes with all of these tags
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub tags: Vec<i32>,
+    /// Only recipes of this household
+    pub household_id: Option<i32>,
+    /// Only recipes without these allergens and suitable for these diets
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub safe_for: Vec<Restriction>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn get_all(
     claims: Claims,
+    pagination: Pagination,
+    Query(mut query): Query<GetAllQuery>,
     Extension(pool): Extension<PgPool>,
-) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Page<GetAllRes>>), (StatusCode, String)> {
+    let sort = pagination.sort(&["name", "created_at", "updated_at"])?;
+
+    // Tags are counted once, a repeated tag would never match
+    query.tags.sort_unstable();
+    query.tags.dedup();
+
+    let updated_since = query.updated_since.map(|updated_since| {
+        let updated_since = updated_since.to_offset(time::UtcOffset::UTC);
+        PrimitiveDateTime::new(updated_since.date(), updated_since.time())
+    });
+
+    let (allergens, diets) = split_restrictions(&query.safe_for);
+
     let recipes = sqlx::query!(
         r#"
-            SELECT recipe.id, recipe.name, recipe.created_at, recipe.updated_at, count(iq.id) AS ingredients
-            FROM recipe
-            LEFT OUTER JOIN recipe_quantity AS iq ON recipe.id = iq.recipe_id
-            WHERE recipe.user_id = $1 GROUP BY recipe.id
+            SELECT id AS "id!", name AS "name!", servings AS "servings!", created_at AS "created_at!",
+            updated_at AS "updated_at!", ingredients AS "ingredients!", household_id,
+            allergens AS "allergens!: Vec<String>", diets AS "diets!: Vec<String>",
+            sort_key AS "sort_key!"
+            FROM (
+                SELECT recipe.id, recipe.name, recipe.servings, recipe.created_at, recipe.updated_at,
+                recipe.household_id, count(iq.id) AS ingredients,
+                recipe_allergens(recipe.id) AS allergens, recipe_diets(recipe.id) AS diets,
+                CASE $2
+                    WHEN 'name' THEN recipe.name
+                    WHEN 'created_at' THEN to_char(recipe.created_at, 'YYYY-MM-DD HH24:MI:SS.US')
+                    ELSE to_char(recipe.updated_at, 'YYYY-MM-DD HH24:MI:SS.US')
+                END AS sort_key
+                FROM recipe
+                LEFT OUTER JOIN recipe_quantity AS iq ON recipe.id = iq.recipe_id
+                WHERE has_access(recipe.user_id, recipe.household_id, $1)
+                AND ($3::timestamp IS NULL OR recipe.updated_at >= $3)
+                AND ($9::integer IS NULL OR recipe.household_id = $9)
+                AND (
+                    SELECT count(DISTINCT rt.tag_id) FROM recipe_tag AS rt
+                    WHERE rt.recipe_id = recipe.id AND rt.tag_id = ANY($8)
+                ) = cardinality($8)
+                GROUP BY recipe.id
+            ) AS recipes
+            WHERE NOT allergens && $10::varchar[] AND diets @> $11::varchar[]
+            AND ($5::text IS NULL OR CASE
+                WHEN $4 = 'desc' THEN (sort_key, id) < ($5, $6)
+                ELSE (sort_key, id) > ($5, $6)
+            END)
+            ORDER BY
+                CASE WHEN $4 = 'desc' THEN sort_key END DESC,
+                CASE WHEN $4 = 'desc' THEN id END DESC,
+                sort_key, id
+            LIMIT $7
         "#,
-        claims.get_sub()
+        claims.get_sub(),
+        sort,
+        updated_since,
+        pagination.order().as_str(),
+        pagination.cursor_key(),
+        pagination.cursor_id(),
+        pagination.fetch_limit(),
+        &query.tags,
+        query.household_id,
+        &allergens,
+        &diets
     )
     .fetch_all(&pool)
     .await
@@ -42,51 +152,119 @@ pub async fn get_all(
         )
     })?;
 
-    Ok((
-        StatusCode::OK,
-        Json(
-            recipes
-                .into_iter()
-                .map(|record| GetAllRes {
-                    id: record.id,
-                    name: record.name,
-                    created_at: record.created_at.assume_utc(),
-                    updated_at: record.updated_at.assume_utc(),
-                    ingredients: record.ingredients.unwrap_or(0),
-                })
-                .collect(),
+    let ids: Vec<i32> = recipes.iter().map(|r| r.id).collect();
+    let tags = get_recipe_tags(
+        &ids,
+        claims.get_sub(),
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed getting recipes".to_string(),
         ),
-    ))
+        &pool,
+    )
+    .await?;
+
+    let page = pagination
+        .page(sort, recipes, |record| (record.sort_key.clone(), record.id))
+        .map(|record| GetAllRes {
+            id: record.id,
+            name: record.name,
+            servings: record.servings,
+            created_at: record.created_at.assume_utc(),
+            updated_at: record.updated_at.assume_utc(),
+            ingredients: record.ingredients,
+            tags: tags_of_recipe(record.id, &tags),
+            household_id: record.household_id,
+            allergens: record.allergens,
+            diets: record.diets,
+        });
+
+    Ok((StatusCode::OK, Json(page)))
 }
 
-#[derive(Deserialize)]
+fn default_servings() -> i32 {
+    1
+}
+
+#[derive(Deserialize, Validate)]
 pub struct CreateReq {
     pub name: String,
+    #[serde(default = "default_servings")]
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    pub servings: i32,
+    #[validate]
     pub ingredients: Vec<IngredientWithQuantity>,
+    #[serde(default)]
+    #[validate]
+    pub steps: Vec<Step>,
+    #[serde(default)]
+    pub tags: Vec<i32>,
+    /// Creates the recipe in a household instead of the personal library
+    pub household_id: Option<i32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct IngredientWithQuantity {
     pub id: i32,
-    pub quantity: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    pub quantity: Decimal,
+    /// Defaults to the unit of the ingredient
+    pub unit_id: Option<i32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct Step {
+    #[validate(length(min = 1, message = "Step text can't be empty"))]
+    pub text: String,
+    /// Timer for the step in seconds
+    #[validate(range(min = 1, message = "Duration has to be at least 1 second"))]
+    pub duration: Option<i32>,
+    /// Ingredients of the recipe used in this step
+    #[serde(default)]
+    pub ingredients: Vec<i32>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn create(
     claims: Claims,
-    extract::Json(payload): extract::Json<CreateReq>,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            get_default_err("Failed creating recipe"),
+            &pool,
+        )
+        .await?;
+    }
+
+    // Ingredients, steps and tags are validated while saving, nothing is kept if one is invalid
+    let mut tx = pool.begin().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed creating recipe".to_string(),
+        )
+    })?;
+
     let insert_result = sqlx::query!(
         r#"
-            INSERT INTO recipe ( name, user_id )
-            VALUES ( $1, $2 )
+            INSERT INTO recipe ( name, servings, user_id, household_id )
+            VALUES ( $1, $2, $3, $4 )
             RETURNING id
         "#,
         payload.name,
-        claims.get_sub()
+        payload.servings,
+        claims.get_sub(),
+        payload.household_id
     )
-    .fetch_one(&pool)
+    .fetch_one(&mut tx)
     .await
     .map_err(|_| {
         (
@@ -99,10 +277,21 @@ pub async fn create(
         insert_result.id,
         claims.get_sub(),
         &payload.ingredients,
-        &pool,
+        &mut tx,
     )
     .await?;
 
+    save_recipe_steps(insert_result.id, &payload.steps, &mut tx).await?;
+
+    save_recipe_tags(insert_result.id, claims.get_sub(), &payload.tags, &mut tx).await?;
+
+    tx.commit().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed creating recipe".to_string(),
+        )
+    })?;
+
     Ok(StatusCode::CREATED)
 }
 
@@ -110,40 +299,115 @@ pub async fn create(
 pub struct GetRes {
     pub id: i32,
     pub name: String,
+    pub servings: i32,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
     pub ingredients: Vec<IngredientDetail>,
+    pub steps: Vec<StepDetail>,
+    pub tags: Vec<TagDetail>,
+    pub household_id: Option<i32>,
+    /// Sum of the ingredients with a known price
+    #[serde(with = "quantity::option")]
+    pub cost: Option<Decimal>,
+    pub nutrition: RecipeNutrition,
+    /// Allergens contained in any ingredient
+    pub allergens: Vec<String>,
+    /// Diets all ingredients are suitable for
+    pub diets: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RecipeNutrition {
+    pub total: Nutrition,
+    pub per_serving: Nutrition,
+    /// Ingredients without nutrition facts, they're missing from the totals
+    pub missing: Vec<i32>,
 }
 
 #[derive(Serialize)]
 pub struct IngredientDetail {
     pub id: i32,
     pub name: String,
+    pub unit_id: i32,
     pub unit: String,
-    pub quantity: i32,
+    #[serde(with = "quantity")]
+    pub quantity: Decimal,
+    /// Cost of the quantity at the latest known price
+    #[serde(with = "quantity::option")]
+    pub cost: Option<Decimal>,
+}
+
+#[derive(Serialize)]
+pub struct StepDetail {
+    pub id: i32,
+    pub text: String,
+    pub duration: Option<i32>,
+    pub sort: i32,
+    pub ingredients: Vec<i32>,
 }
 
 impl GetRes {
-    pub fn new(recipe: RecipeQuery, mut ingredients: Vec<IngredientForRecipeQuery>) -> Self {
-        ingredients.sort_by(|a, b| a.sort.cmp(&b.sort));
-        let ingredients = ingredients
+    pub fn new(
+        recipe: RecipeQuery,
+        mut ingredients: Vec<IngredientForRecipeQuery>,
+        steps: Vec<StepDetail>,
+        tags: Vec<TagDetail>,
+        prices: &[Price],
+        facts: &[IngredientNutrition],
+        units: &[Unit],
+    ) -> Self {
+        ingredients.sort_by_key(|i| i.sort);
+
+        let mut total = Nutrition::default();
+        let mut missing = Vec::new();
+        for i in ingredients.iter() {
+            let nutrition = find_unit(i.unit_id, units)
+                .ok()
+                .and_then(|unit| nutrition_of(i.quantity, unit, i.id, i.density, facts, units));
+            match nutrition {
+                Some(nutrition) => total += nutrition,
+                None => missing.push(i.id),
+            }
+        }
+        let nutrition = RecipeNutrition {
+            total: total.round(),
+            per_serving: total
+                .scale(Decimal::ONE / Decimal::from(recipe.servings))
+                .round(),
+            missing,
+        };
+
+        let ingredients: Vec<IngredientDetail> = ingredients
             .into_iter()
             .map(|i| IngredientDetail {
+                cost: find_unit(i.unit_id, units)
+                    .ok()
+                    .and_then(|unit| cost(i.quantity, unit, i.id, i.density, prices, units)),
                 id: i.id,
                 name: i.name,
+                unit_id: i.unit_id,
                 unit: i.unit,
                 quantity: i.quantity,
             })
             .collect();
+        let cost = total_cost(ingredients.iter().map(|i| i.cost));
 
         GetRes {
             id: recipe.id,
             name: recipe.name,
+            servings: recipe.servings,
             created_at: recipe.created_at.assume_utc(),
             updated_at: recipe.updated_at.assume_utc(),
             ingredients,
+            steps,
+            tags,
+            household_id: recipe.household_id,
+            cost,
+            nutrition,
+            allergens: recipe.allergens,
+            diets: recipe.diets,
         }
     }
 }
@@ -151,29 +415,50 @@ impl GetRes {
 pub struct RecipeQuery {
     pub id: i32,
     pub name: String,
+    pub servings: i32,
     pub created_at: PrimitiveDateTime,
     pub updated_at: PrimitiveDateTime,
+    pub household_id: Option<i32>,
+    pub allergens: Vec<String>,
+    pub diets: Vec<String>,
 }
 
 pub struct IngredientForRecipeQuery {
     pub id: i32,
     pub name: String,
+    pub unit_id: i32,
     pub unit: String,
-    pub quantity: i32,
+    pub quantity: Decimal,
     pub sort: i32,
+    pub density: Option<Decimal>,
+}
+
+#[derive(Deserialize)]
+pub struct GetQuery {
+    pub servings: Option<i32>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn get(
     claims: Claims,
     Path(id): Path<i32>,
+    Query(query): Query<GetQuery>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<(StatusCode, Json<GetRes>), (StatusCode, String)> {
-    let recipe: RecipeQuery = sqlx::query_as!(
+    if matches!(query.servings, Some(servings) if servings < 1) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Servings have to be at least 1".to_string(),
+        ));
+    }
+
+    let mut recipe: RecipeQuery = sqlx::query_as!(
         RecipeQuery,
         r#"
-            SELECT id, name, created_at, updated_at FROM recipe
-            WHERE id = $1 AND user_id = $2
+            SELECT id, name, servings, created_at, updated_at, household_id,
+            recipe_allergens(id) AS "allergens!: Vec<String>", recipe_diets(id) AS "diets!: Vec<String>"
+            FROM recipe
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
         "#,
         id,
         claims.get_sub()
@@ -188,12 +473,13 @@ pub async fn get(
     })?
     .ok_or((StatusCode::NOT_FOUND, "Recipe not found".to_string()))?;
 
-    let ingredients = sqlx::query_as!(
+    let mut ingredients = sqlx::query_as!(
         IngredientForRecipeQuery,
         r#"
-            SELECT i.id, i.name, u.name AS unit, inq.quantity, i.sort FROM recipe_quantity AS inq
+            SELECT i.id, i.name, u.id AS unit_id, u.name AS unit, inq.quantity, i.sort, i.density
+            FROM recipe_quantity AS inq
             INNER JOIN ingredient AS i ON inq.ingredient_id = i.id
-            INNER JOIN unit AS u ON i.unit_id = u.id
+            INNER JOIN unit AS u ON inq.unit_id = u.id
             WHERE inq.recipe_id = $1
         "#,
         recipe.id
@@ -207,40 +493,115 @@ pub async fn get(
         )
     })?;
 
-    Ok((StatusCode::OK, Json(GetRes::new(recipe, ingredients))))
+    let steps = sqlx::query_as!(
+        StepDetail,
+        r#"
+            SELECT rs.id, rs.text, rs.duration, rs.sort,
+            COALESCE(array_agg(rsi.ingredient_id) FILTER (WHERE rsi.id IS NOT NULL), '{}') AS "ingredients!"
+            FROM recipe_step AS rs
+            LEFT JOIN recipe_step_ingredient AS rsi ON rs.id = rsi.recipe_step_id
+            WHERE rs.recipe_id = $1
+            GROUP BY rs.id
+            ORDER BY rs.sort
+        "#,
+        recipe.id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed getting recipe".to_string(),
+        )
+    })?;
+
+    let tags = get_recipe_tags(
+        &[recipe.id],
+        claims.get_sub(),
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed getting recipe".to_string(),
+        ),
+        &pool,
+    )
+    .await?;
+
+    let default_err = get_default_err("Failed getting recipe");
+    let ingredient_ids: Vec<i32> = ingredients.iter().map(|i| i.id).collect();
+    let prices = get_latest_prices(
+        &ingredient_ids,
+        claims.get_sub(),
+        None,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+    let facts = get_nutrition(&ingredient_ids, default_err.clone(), &pool).await?;
+    let units = get_units(claims.get_sub(), default_err, &pool).await?;
+
+    if let Some(servings) = query.servings {
+        for ingredient in ingredients.iter_mut() {
+            ingredient.quantity = quantity::check_bounds(scale_quantity(
+                ingredient.quantity,
+                recipe.servings,
+                servings,
+            ))?;
+        }
+        recipe.servings = servings;
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(GetRes::new(
+            recipe,
+            ingredients,
+            steps,
+            tags_of_recipe(id, &tags),
+            &prices,
+            &facts,
+            &units,
+        )),
+    ))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateReq {
     pub name: Option<String>,
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    pub servings: Option<i32>,
+    #[validate]
     pub ingredients: Option<Vec<IngredientWithQuantity>>,
+    #[validate]
+    pub steps: Option<Vec<Step>>,
+    pub tags: Option<Vec<i32>>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn update(
     claims: Claims,
     Path(id): Path<i32>,
-    extract::Json(payload): extract::Json<UpdateReq>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    sqlx::query!(
-        r#"SELECT id FROM recipe WHERE id = $1 AND user_id = $2"#,
+    validate_recipe_access(
         id,
-        claims.get_sub()
+        claims.get_sub(),
+        get_default_err("Failed updating recipe"),
+        &pool,
     )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| {
+    .await?;
+
+    // Replaced ingredients, steps and tags are only removed if the new ones are valid
+    let mut tx = pool.begin().await.map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             "Failed updating recipe".to_string(),
         )
-    })?
-    .ok_or((StatusCode::NOT_FOUND, "Recipe not found".to_string()))?;
+    })?;
 
     if let Some(ref name) = payload.name {
         sqlx::query!(r#"UPDATE recipe SET name = $1 WHERE id = $2"#, name, id,)
-            .execute(&pool)
+            .execute(&mut tx)
             .await
             .map_err(|_| {
                 (
@@ -250,10 +611,26 @@ pub async fn update(
             })?;
     }
 
+    if let Some(servings) = payload.servings {
+        sqlx::query!(
+            r#"UPDATE recipe SET servings = $1 WHERE id = $2"#,
+            servings,
+            id,
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed updating recipe".to_string(),
+            )
+        })?;
+    }
+
     if let Some(ref ingredients) = payload.ingredients {
         {
             sqlx::query!(r#"DELETE FROM recipe_quantity WHERE recipe_id = $1"#, id)
-                .execute(&pool)
+                .execute(&mut tx)
                 .await
                 .map_err(|_| {
                     (
@@ -262,18 +639,79 @@ pub async fn update(
                     )
                 })?;
 
-            save_recipe_ingredients(id, claims.get_sub(), ingredients, &pool).await?;
+            save_recipe_ingredients(id, claims.get_sub(), ingredients, &mut tx).await?;
+
+            // Steps can't reference ingredients which were removed from the recipe
+            sqlx::query!(
+                r#"
+                    DELETE FROM recipe_step_ingredient AS rsi
+                    USING recipe_step AS rs
+                    WHERE rsi.recipe_step_id = rs.id AND rs.recipe_id = $1
+                    AND rsi.ingredient_id NOT IN (
+                        SELECT ingredient_id FROM recipe_quantity WHERE recipe_id = $1
+                    )
+                "#,
+                id
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed updating recipe".to_string(),
+                )
+            })?;
         }
     }
 
-    if payload.name.is_none() && payload.ingredients.is_some() {
+    if let Some(ref steps) = payload.steps {
+        sqlx::query!(r#"DELETE FROM recipe_step WHERE recipe_id = $1"#, id)
+            .execute(&mut tx)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed updating recipe".to_string(),
+                )
+            })?;
+
+        save_recipe_steps(id, steps, &mut tx).await?;
+    }
+
+    if let Some(ref tags) = payload.tags {
+        // Only replaces the user's own tags, other household members keep theirs
+        sqlx::query!(
+            r#"
+                DELETE FROM recipe_tag AS rt
+                USING tag AS t
+                WHERE rt.tag_id = t.id AND rt.recipe_id = $1 AND t.user_id = $2
+            "#,
+            id,
+            claims.get_sub()
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed updating recipe".to_string(),
+            )
+        })?;
+
+        save_recipe_tags(id, claims.get_sub(), tags, &mut tx).await?;
+    }
+
+    if payload.name.is_none()
+        && payload.servings.is_none()
+        && (payload.ingredients.is_some() || payload.steps.is_some() || payload.tags.is_some())
+    {
         let updated = OffsetDateTime::now_utc();
         sqlx::query!(
             r#"UPDATE recipe SET updated_at = $1 WHERE id = $2"#,
             PrimitiveDateTime::new(updated.date(), updated.time()),
             id,
         )
-        .execute(&pool)
+        .execute(&mut tx)
         .await
         .map_err(|_| {
             (
@@ -283,6 +721,13 @@ pub async fn update(
         })?;
     }
 
+    tx.commit().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed updating recipe".to_string(),
+        )
+    })?;
+
     Ok(StatusCode::OK)
 }
 
@@ -295,7 +740,7 @@ pub async fn delete(
     sqlx::query!(
         r#"
             DELETE FROM recipe
-            WHERE id = $1 AND user_id = $2
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
             RETURNING id
         "#,
         id,
@@ -313,3 +758,86 @@ pub async fn delete(
 
     Ok(StatusCode::OK)
 }
+
+#[derive(Deserialize)]
+pub struct SortStepReq {
+    new_sort: i32,
+}
+
+#[axum_macros::debug_handler]
+pub async fn sort_step(
+    claims: Claims,
+    Path((id, step_id)): Path<(i32, i32)>,
+    extract::Json(mut payload): extract::Json<SortStepReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed sorting step");
+
+    validate_recipe_access(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    // Last step
+    let max = get_last_step_by_sort(id, default_err.clone(), &pool).await?;
+
+    // Old position of step
+    let old_sort = sqlx::query!(
+        r#"SELECT sort FROM recipe_step WHERE id = $1 AND recipe_id = $2"#,
+        step_id,
+        id,
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Step not found".to_string()))?
+    .sort;
+
+    if payload.new_sort < 1 {
+        payload.new_sort = 1;
+    } else if let Some(max) = max {
+        // Step is already at last position, no need to sort
+        if payload.new_sort > max.sort && old_sort == max.sort {
+            return Err((StatusCode::BAD_REQUEST, "Nothing to sort".to_string()));
+        } else if payload.new_sort > max.sort {
+            payload.new_sort = max.sort;
+        }
+    }
+
+    if payload.new_sort == old_sort {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to sort".to_string()));
+    }
+
+    if payload.new_sort < old_sort {
+        let add_sort = sqlx::query!(
+            r#"SELECT id, sort FROM recipe_step WHERE sort >= $1 AND sort < $2 AND recipe_id = $3 ORDER BY sort"#,
+            payload.new_sort,
+            old_sort,
+            id
+        )
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        for step in add_sort.into_iter() {
+            update_step_sort(step.id, step.sort + 1, default_err.clone(), &pool).await?;
+        }
+    }
+
+    if payload.new_sort > old_sort {
+        let subtract_sort = sqlx::query!(
+            r#"SELECT id, sort FROM recipe_step WHERE sort > $1 AND sort <= $2 AND recipe_id = $3 ORDER BY sort"#,
+            old_sort,
+            payload.new_sort,
+            id
+        )
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        for step in subtract_sort.into_iter() {
+            update_step_sort(step.id, step.sort - 1, default_err.clone(), &pool).await?;
+        }
+    }
+
+    update_step_sort(step_id, payload.new_sort, default_err, &pool).await?;
+
+    Ok(StatusCode::OK)
+}
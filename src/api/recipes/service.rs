@@ -1,21 +1,39 @@
-use super::api::IngredientWithQuantity;
+use super::api::{IngredientWithQuantity, Step};
+use crate::api::units::service::{check_unit, get_units};
 use axum::http::StatusCode;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+/// Ingredients have to be accessible to the user, household recipes can only use ingredients of
+/// their household
 pub async fn save_recipe_ingredients(
     recipe_id: i32,
     user_id: Uuid,
     ingredients: &[IngredientWithQuantity],
-    pool: &PgPool,
+    conn: &mut PgConnection,
 ) -> Result<(), (StatusCode, String)> {
+    let units = get_units(
+        user_id,
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed saving ingredients for recipe".to_string(),
+        ),
+        &mut *conn,
+    )
+    .await?;
+
     for ingredient in ingredients.iter() {
-        sqlx::query!(
-            r#"SELECT id FROM ingredient WHERE id = $1 AND user_id = $2"#,
+        let record = sqlx::query!(
+            r#"
+                SELECT i.id, i.unit_id, i.density FROM ingredient AS i, recipe AS r
+                WHERE i.id = $1 AND r.id = $3 AND has_access(i.user_id, i.household_id, $2)
+                AND (r.household_id IS NULL OR i.household_id = r.household_id)
+            "#,
             ingredient.id,
-            user_id
+            user_id,
+            recipe_id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|_| {
             (
@@ -23,17 +41,22 @@ pub async fn save_recipe_ingredients(
                 format!("Ingredient with id {} not found", ingredient.id),
             )
         })?;
+
+        let unit_id = ingredient.unit_id.unwrap_or(record.unit_id);
+        check_unit(unit_id, record.id, record.unit_id, record.density, &units)?;
+
         sqlx::query!(
             r#"
-                INSERT INTO recipe_quantity ( recipe_id, ingredient_id, quantity )
-                VALUES ( $1, $2, $3 )
+                INSERT INTO recipe_quantity ( recipe_id, ingredient_id, quantity, unit_id )
+                VALUES ( $1, $2, $3, $4 )
                 RETURNING id
             "#,
             recipe_id,
             ingredient.id,
-            ingredient.quantity
+            ingredient.quantity,
+            unit_id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|_| {
             (
@@ -44,3 +67,192 @@ pub async fn save_recipe_ingredients(
     }
     Ok(())
 }
+
+pub async fn save_recipe_steps(
+    recipe_id: i32,
+    steps: &[Step],
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    let default_err = (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed saving steps for recipe".to_string(),
+    );
+
+    let recipe_ingredients: Vec<i32> = sqlx::query!(
+        r#"SELECT ingredient_id FROM recipe_quantity WHERE recipe_id = $1"#,
+        recipe_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?
+    .into_iter()
+    .map(|r| r.ingredient_id)
+    .collect();
+
+    for (index, step) in steps.iter().enumerate() {
+        if let Some(id) = step
+            .ingredients
+            .iter()
+            .find(|id| !recipe_ingredients.contains(id))
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Ingredient with id {} is not part of the recipe", id),
+            ));
+        }
+
+        let inserted = sqlx::query!(
+            r#"
+                INSERT INTO recipe_step ( recipe_id, text, duration, sort )
+                VALUES ( $1, $2, $3, $4 )
+                RETURNING id
+            "#,
+            recipe_id,
+            step.text,
+            step.duration,
+            index as i32 + 1
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO recipe_step_ingredient ( recipe_step_id, ingredient_id )
+                SELECT $1, * FROM UNNEST($2::integer[])
+            "#,
+            inserted.id,
+            &step.ingredients
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+    Ok(())
+}
+
+pub async fn update_step_sort(
+    id: i32,
+    new_sort: i32,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"UPDATE recipe_step SET sort = $1 WHERE id = $2"#,
+        new_sort,
+        id
+    )
+    .execute(pool)
+    .await
+    .map_err(|_| default_err)
+    .map(|_| ())
+}
+
+pub struct StepSort {
+    pub sort: i32,
+}
+
+pub async fn get_last_step_by_sort(
+    recipe_id: i32,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<Option<StepSort>, (StatusCode, String)> {
+    sqlx::query_as!(
+        StepSort,
+        r#"SELECT sort FROM recipe_step WHERE recipe_id = $1 ORDER BY sort DESC LIMIT 1"#,
+        recipe_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err.clone())
+}
+
+pub async fn save_recipe_tags(
+    recipe_id: i32,
+    user_id: Uuid,
+    tags: &[i32],
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    for tag_id in tags.iter() {
+        sqlx::query!(
+            r#"SELECT id FROM tag WHERE id = $1 AND user_id = $2"#,
+            tag_id,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Tag with id {} not found", tag_id),
+            )
+        })?;
+        sqlx::query!(
+            r#"
+                INSERT INTO recipe_tag ( recipe_id, tag_id )
+                VALUES ( $1, $2 )
+                ON CONFLICT DO NOTHING
+            "#,
+            recipe_id,
+            tag_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed saving tags for recipe".to_string(),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+pub struct RecipeTag {
+    pub recipe_id: i32,
+    pub id: i32,
+    pub name: String,
+}
+
+/// Tags are personal, so only the tags of the user are returned for household recipes
+pub async fn get_recipe_tags(
+    recipe_ids: &[i32],
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<Vec<RecipeTag>, (StatusCode, String)> {
+    sqlx::query_as!(
+        RecipeTag,
+        r#"
+            SELECT rt.recipe_id, t.id, t.name
+            FROM recipe_tag AS rt
+            JOIN tag AS t ON rt.tag_id = t.id
+            WHERE rt.recipe_id = ANY($1) AND t.user_id = $2
+            ORDER BY t.name
+        "#,
+        recipe_ids,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|_| default_err)
+}
+
+/// Makes sure the user has access to the recipe, personally or through a household
+pub async fn validate_recipe_access(
+    recipe_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"SELECT id FROM recipe WHERE id = $1 AND has_access(user_id, household_id, $2)"#,
+        recipe_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Recipe not found".to_string()))
+    .map(|_| ())
+}
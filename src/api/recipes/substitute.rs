@@ -0,0 +1,200 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    recipes::service::validate_recipe_access,
+    substitutions::service::{get_substitution, substitute_quantity},
+    units::{
+        quantity::check_bounds,
+        service::{convert, find_unit, get_units},
+    },
+};
+use axum::{
+    extract::{self, Path},
+    http::StatusCode,
+    Extension,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+#[derive(Deserialize)]
+pub struct SubstituteReq {
+    ingredient_id: i32,
+    substitute_id: i32,
+}
+
+/// Replaces an ingredient of a recipe and its steps by a substitute, quantities are recomputed
+/// with the ratio of the substitution rule
+#[axum_macros::debug_handler]
+pub async fn substitute(
+    claims: Claims,
+    Path(id): Path<i32>,
+    extract::Json(payload): extract::Json<SubstituteReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed substituting ingredient");
+
+    validate_recipe_access(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    // Household recipes can only use ingredients of their household
+    sqlx::query!(
+        r#"
+            SELECT i.id FROM ingredient AS i, recipe AS r
+            WHERE i.id = $1 AND r.id = $3 AND has_access(i.user_id, i.household_id, $2)
+            AND (r.household_id IS NULL OR i.household_id = r.household_id)
+        "#,
+        payload.substitute_id,
+        claims.get_sub(),
+        id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Ingredient not found".to_string()))?;
+
+    let substitution = get_substitution(
+        payload.ingredient_id,
+        payload.substitute_id,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let units = get_units(claims.get_sub(), default_err.clone(), &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT id, quantity, unit_id FROM recipe_quantity
+            WHERE recipe_id = $1 AND ingredient_id = $2
+            FOR UPDATE
+        "#,
+        id,
+        payload.ingredient_id
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    if quantities.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient not found in recipe".to_string(),
+        ));
+    }
+
+    // A recipe already using the substitute gets the substituted quantities added in its unit
+    let existing = sqlx::query!(
+        r#"
+            SELECT id, quantity, unit_id FROM recipe_quantity
+            WHERE recipe_id = $1 AND ingredient_id = $2
+            ORDER BY id
+            LIMIT 1
+            FOR UPDATE
+        "#,
+        id,
+        payload.substitute_id
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // Id, quantity and unit of the row all quantities end up in
+    let mut merged = existing.map(|e| (e.id, e.quantity, e.unit_id));
+    for quantity in quantities.into_iter() {
+        let substituted =
+            substitute_quantity(quantity.quantity, quantity.unit_id, &substitution, &units)?;
+
+        merged = Some(match merged {
+            Some((target_id, total, unit_id)) => {
+                let added = convert(
+                    substituted,
+                    find_unit(substitution.substitute_unit_id, &units)?,
+                    find_unit(unit_id, &units)?,
+                    substitution.substitute_density,
+                );
+                let total = check_bounds(added.and_then(|added| total.checked_add(added)))?;
+                (target_id, total, unit_id)
+            }
+            None => (quantity.id, substituted, substitution.substitute_unit_id),
+        });
+    }
+
+    if let Some((target_id, total, unit_id)) = merged {
+        sqlx::query!(
+            r#"
+                DELETE FROM recipe_quantity
+                WHERE recipe_id = $1 AND ingredient_id = $2 AND id <> $3
+            "#,
+            id,
+            payload.ingredient_id,
+            target_id
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        sqlx::query!(
+            r#"
+                UPDATE recipe_quantity SET ingredient_id = $1, quantity = $2, unit_id = $3
+                WHERE id = $4
+            "#,
+            payload.substitute_id,
+            total,
+            unit_id,
+            target_id
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    // Steps already using the substitute only keep one reference to it
+    sqlx::query!(
+        r#"
+            DELETE FROM recipe_step_ingredient AS rsi
+            USING recipe_step AS rs
+            WHERE rsi.recipe_step_id = rs.id AND rs.recipe_id = $1 AND rsi.ingredient_id = $2
+            AND EXISTS (
+                SELECT 1 FROM recipe_step_ingredient
+                WHERE recipe_step_id = rs.id AND ingredient_id = $3
+            )
+        "#,
+        id,
+        payload.ingredient_id,
+        payload.substitute_id
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            UPDATE recipe_step_ingredient AS rsi SET ingredient_id = $3
+            FROM recipe_step AS rs
+            WHERE rsi.recipe_step_id = rs.id AND rs.recipe_id = $1 AND rsi.ingredient_id = $2
+        "#,
+        id,
+        payload.ingredient_id,
+        payload.substitute_id
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let updated = OffsetDateTime::now_utc();
+    sqlx::query!(
+        r#"UPDATE recipe SET updated_at = $1 WHERE id = $2"#,
+        PrimitiveDateTime::new(updated.date(), updated.time()),
+        id,
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
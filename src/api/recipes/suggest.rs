@@ -0,0 +1,174 @@
+use crate::api::{
+    auth::Claims,
+    global::{comma_separated, get_default_err},
+    pantry::service::get_pantry_stock,
+    units::{
+        quantity,
+        service::{convert, find_unit, get_units, normalize, scale_quantity},
+    },
+};
+use axum::{extract::Query, http::StatusCode, Extension, Json};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::cmp::Ordering;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    /// Ingredients at hand, assumed to be available in any quantity. Defaults to the pantry.
+    #[serde(default, deserialize_with = "comma_separated")]
+    ingredients: Vec<i32>,
+    /// Servings to cook, defaults to the servings of each recipe
+    servings: Option<i32>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SuggestRes {
+    id: i32,
+    name: String,
+    servings: i32,
+    /// Share of the required ingredients that's available, from 0 to 1
+    coverage: f32,
+    missing: Vec<Missing>,
+}
+
+#[derive(Serialize)]
+pub struct Missing {
+    id: i32,
+    name: String,
+    #[serde(with = "quantity")]
+    quantity: Decimal,
+    unit: String,
+}
+
+/// Ranks the recipes of the user by how much of them can be cooked with what's at hand
+#[axum_macros::debug_handler]
+pub async fn suggest(
+    claims: Claims,
+    Query(query): Query<SuggestQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<SuggestRes>>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed suggesting recipes");
+
+    if query.servings.is_some_and(|servings| servings < 1) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Servings have to be at least 1".to_string(),
+        ));
+    }
+
+    let recipes = sqlx::query!(
+        r#"SELECT id, name, servings FROM recipe WHERE has_access(user_id, household_id, $1)"#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let ids: Vec<i32> = recipes.iter().map(|r| r.id).collect();
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT rq.recipe_id, rq.quantity, rq.unit_id, i.id AS ingredient_id, i.name, i.density
+            FROM recipe_quantity AS rq
+            JOIN ingredient AS i ON rq.ingredient_id = i.id
+            WHERE rq.recipe_id = ANY($1)
+        "#,
+        &ids
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let pantry = match query.ingredients.is_empty() {
+        true => {
+            let ingredient_ids: Vec<i32> = quantities.iter().map(|q| q.ingredient_id).collect();
+            get_pantry_stock(
+                &ingredient_ids,
+                claims.get_sub(),
+                default_err.clone(),
+                &pool,
+            )
+            .await?
+        }
+        false => Vec::new(),
+    };
+
+    let units = get_units(claims.get_sub(), default_err, &pool).await?;
+
+    let mut suggestions = Vec::new();
+    for recipe in recipes.into_iter() {
+        let servings = query.servings.unwrap_or(recipe.servings);
+        let mut covered = Decimal::ZERO;
+        let mut required = 0;
+        let mut missing = Vec::new();
+
+        for q in quantities.iter().filter(|q| q.recipe_id == recipe.id) {
+            let unit = find_unit(q.unit_id, &units)?;
+            let needed =
+                quantity::check_bounds(scale_quantity(q.quantity, recipe.servings, servings))?;
+            required += 1;
+
+            let available = match query.ingredients.is_empty() {
+                true => pantry
+                    .iter()
+                    .filter(|p| p.ingredient_id == q.ingredient_id)
+                    .filter_map(|p| {
+                        convert(
+                            p.quantity,
+                            find_unit(p.unit_id, &units).ok()?,
+                            unit,
+                            q.density,
+                        )
+                    })
+                    .sum(),
+                false if query.ingredients.contains(&q.ingredient_id) => needed,
+                false => Decimal::ZERO,
+            };
+
+            if available >= needed {
+                covered += Decimal::ONE;
+            } else {
+                covered += available / needed;
+                let (quantity, unit) = normalize(needed - available, unit, &units);
+                missing.push(Missing {
+                    id: q.ingredient_id,
+                    name: q.name.clone(),
+                    quantity: quantity.round_dp(quantity::SCALE).normalize(),
+                    unit: unit.name.clone(),
+                });
+            }
+        }
+
+        // Nothing to suggest without knowing the ingredients
+        if required == 0 {
+            continue;
+        }
+
+        suggestions.push(SuggestRes {
+            id: recipe.id,
+            name: recipe.name,
+            servings,
+            coverage: (covered / Decimal::from(required))
+                .round_dp(2)
+                .to_f32()
+                .unwrap_or_default(),
+            missing,
+        });
+    }
+
+    suggestions.sort_by(|a, b| {
+        b.coverage
+            .partial_cmp(&a.coverage)
+            .unwrap_or(Ordering::Equal)
+            .then(a.missing.len().cmp(&b.missing.len()))
+            .then(a.name.cmp(&b.name))
+    });
+    suggestions.truncate(query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT));
+
+    Ok((StatusCode::OK, Json(suggestions)))
+}
@@ -0,0 +1,4 @@
+pub mod api;
+pub use api::search_ingredients;
+pub use api::search_recipes;
+mod service;
@@ -0,0 +1,164 @@
+use crate::api::{
+    auth::Claims,
+    global::{comma_separated, get_default_err},
+    search::service::{to_any_prefix_query, to_prefix_query},
+};
+use axum::{extract::Query, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct SearchRecipesQuery {
+    #[serde(default)]
+    q: String,
+    /// Only recipes containing all of these ingredients
+    #[serde(default, deserialize_with = "comma_separated")]
+    ingredients: Vec<i32>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SearchRecipesRes {
+    id: i32,
+    name: String,
+    servings: i32,
+    rank: f32,
+}
+
+#[axum_macros::debug_handler]
+pub async fn search_recipes(
+    claims: Claims,
+    Query(mut query): Query<SearchRecipesQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<SearchRecipesRes>>), (StatusCode, String)> {
+    let ts_query = to_prefix_query(&query.q);
+
+    if ts_query.is_empty() && query.ingredients.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to search for".to_string()));
+    }
+
+    // Every ingredient is counted once below
+    query.ingredients.sort_unstable();
+    query.ingredients.dedup();
+
+    // Candidates come from the indexed columns, the combined document is only built for them.
+    // Matches in the name weigh more than matches in ingredients and steps
+    let recipes = sqlx::query_as!(
+        SearchRecipesRes,
+        r#"
+            WITH candidate AS (
+                SELECT id AS recipe_id FROM recipe WHERE search @@ to_tsquery('simple', $5)
+                UNION
+                SELECT rq.recipe_id FROM ingredient AS i
+                JOIN recipe_quantity AS rq ON rq.ingredient_id = i.id
+                WHERE i.search @@ to_tsquery('simple', $5)
+                UNION
+                SELECT recipe_id FROM recipe_step WHERE search @@ to_tsquery('simple', $5)
+            )
+            SELECT r.id, r.name, r.servings, ts_rank(d.document, to_tsquery('simple', $2)) AS "rank!"
+            FROM recipe AS r
+            CROSS JOIN LATERAL (
+                SELECT setweight(r.search, 'A')
+                || setweight(COALESCE((
+                    SELECT tsvector_agg(i.search) FROM recipe_quantity AS rq
+                    JOIN ingredient AS i ON rq.ingredient_id = i.id
+                    WHERE rq.recipe_id = r.id
+                ), ''), 'B')
+                || setweight(COALESCE((
+                    SELECT tsvector_agg(rs.search) FROM recipe_step AS rs WHERE rs.recipe_id = r.id
+                ), ''), 'C') AS document
+            ) AS d
+            WHERE has_access(r.user_id, r.household_id, $1)
+            AND ($2 = '' OR r.id IN (SELECT recipe_id FROM candidate))
+            AND ($2 = '' OR d.document @@ to_tsquery('simple', $2))
+            AND (
+                SELECT count(DISTINCT rq.ingredient_id) FROM recipe_quantity AS rq
+                WHERE rq.recipe_id = r.id AND rq.ingredient_id = ANY($3)
+            ) = cardinality($3)
+            ORDER BY "rank!" DESC, r.name
+            LIMIT $4
+        "#,
+        claims.get_sub(),
+        ts_query,
+        &query.ingredients,
+        query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        to_any_prefix_query(&query.q)
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed searching recipes"))?;
+
+    Ok((StatusCode::OK, Json(recipes)))
+}
+
+#[derive(Deserialize)]
+pub struct SearchIngredientsQuery {
+    q: String,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct SearchIngredientsRes {
+    id: i32,
+    name: String,
+    unit: SearchIngredientsResUnit,
+    rank: f32,
+}
+
+#[derive(Serialize)]
+pub struct SearchIngredientsResUnit {
+    id: i32,
+    name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn search_ingredients(
+    claims: Claims,
+    Query(query): Query<SearchIngredientsQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<SearchIngredientsRes>>), (StatusCode, String)> {
+    let ts_query = to_prefix_query(&query.q);
+
+    if ts_query.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to search for".to_string()));
+    }
+
+    let ingredients = sqlx::query!(
+        r#"
+            SELECT i.id, i.name, u.id AS unit_id, u.name AS unit_name,
+            ts_rank(i.search, to_tsquery('simple', $2)) AS "rank!"
+            FROM ingredient AS i
+            INNER JOIN unit AS u ON i.unit_id = u.id
+            WHERE has_access(i.user_id, i.household_id, $1) AND i.search @@ to_tsquery('simple', $2)
+            ORDER BY "rank!" DESC, i.sort
+            LIMIT $3
+        "#,
+        claims.get_sub(),
+        ts_query,
+        query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed searching ingredients"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(
+            ingredients
+                .into_iter()
+                .map(|record| SearchIngredientsRes {
+                    id: record.id,
+                    name: record.name,
+                    unit: SearchIngredientsResUnit {
+                        id: record.unit_id,
+                        name: record.unit_name,
+                    },
+                    rank: record.rank,
+                })
+                .collect(),
+        ),
+    ))
+}
@@ -0,0 +1,18 @@
+fn prefix_terms(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word))
+        .collect()
+}
+
+/// Builds a tsquery matching all words of the input as prefixes, so "tom sau" finds "tomato sauce"
+pub fn to_prefix_query(input: &str) -> String {
+    prefix_terms(input).join(" & ")
+}
+
+/// Builds a tsquery matching any word of the input as prefix, to look up candidates in the
+/// indexes of the single columns
+pub fn to_any_prefix_query(input: &str) -> String {
+    prefix_terms(input).join(" | ")
+}
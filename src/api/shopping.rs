@@ -1,16 +1,40 @@
 pub mod api;
+pub mod cleanup;
+pub mod duplicate;
+pub mod events;
+pub mod finish;
+pub mod generate;
 pub mod ingredient;
+pub mod member;
+pub mod merge;
 pub mod quantity;
 pub mod recipe;
+pub mod substitute;
+pub mod sync;
 pub use api::create;
 pub use api::delete;
 pub use api::get;
 pub use api::get_all;
+pub use api::update;
+pub use duplicate::duplicate;
+pub use events::events;
+pub use events::ShoppingEvents;
+pub use finish::finish;
+pub use generate::generate;
 pub use ingredient::add_ingredient;
+pub use ingredient::assign_store;
 pub use ingredient::check_ingredient;
+pub use ingredient::check_ingredients;
 pub use ingredient::delete_ingredient;
+pub use member::add_member;
+pub use member::delete_member;
+pub use member::get_members;
+pub use member::update_member;
+pub use merge::merge;
 pub use quantity::delete_quantity;
 pub use quantity::update_quantity;
 pub use recipe::add_recipe;
 pub use recipe::delete_recipe;
-mod service;
+pub use substitute::substitute;
+pub use sync::sync;
+pub mod service;
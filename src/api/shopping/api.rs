@@ -1,11 +1,29 @@
-use crate::api::{auth::Claims, global::get_default_err};
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, Page, Pagination, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+    pantry::service::get_pantry_stock,
+    prices::service::{cost, get_latest_prices, total_cost},
+    shopping::{
+        events::ShoppingEvents,
+        service::{get_shopping_units, validate_shopping_id, Role},
+    },
+    stores::service::validate_store_id,
+    units::{
+        quantity,
+        service::{convert, find_unit, normalize, sum},
+    },
+};
 use axum::{
-    extract::{self, Path},
+    extract::{self, Path, Query},
     http::StatusCode,
     Extension, Json,
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use time::OffsetDateTime;
+use validator::Validate;
 
 #[derive(Serialize)]
 pub struct GetAllRes {
@@ -13,21 +31,65 @@ pub struct GetAllRes {
     pub name: String,
     pub ingredients: i64,
     pub checked: i64,
+    pub role: String,
+    pub household_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    /// Only lists with (`true`) or without (`false`) unchecked ingredients
+    pub unchecked: Option<bool>,
 }
 
 #[axum_macros::debug_handler]
 pub async fn get_all(
     claims: Claims,
+    pagination: Pagination,
+    Query(query): Query<GetAllQuery>,
     Extension(pool): Extension<PgPool>,
-) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Page<GetAllRes>>), (StatusCode, String)> {
+    let sort = pagination.sort(&["id", "name"])?;
+
     let recipes = sqlx::query!(
       r#"
-        SELECT shopping.id, shopping.name, count(si.id) filter (where si.checked) AS checked, count(si.id) AS ingredients
-        FROM shopping
-        LEFT OUTER JOIN shopping_ingredient AS si ON shopping.id = si.shopping_id
-        WHERE shopping.user_id = $1 GROUP BY shopping.id
+        SELECT id AS "id!", name AS "name!", checked AS "checked!", ingredients AS "ingredients!",
+        role AS "role!", household_id, sort_key AS "sort_key!"
+        FROM (
+            SELECT shopping.id, shopping.name, shopping.household_id,
+            count(si.id) filter (where si.checked) AS checked, count(si.id) AS ingredients,
+            CASE
+                WHEN shopping.household_id IS NULL AND shopping.user_id = $1 THEN 'owner'
+                WHEN min(hm.role) = 'admin' THEN 'owner'
+                WHEN min(hm.role) = 'member' THEN 'editor'
+                ELSE min(sm.role)
+            END AS role,
+            CASE $2 WHEN 'name' THEN shopping.name ELSE lpad(shopping.id::text, 10, '0') END AS sort_key
+            FROM shopping
+            LEFT OUTER JOIN shopping_ingredient AS si ON shopping.id = si.shopping_id
+            LEFT OUTER JOIN shopping_member AS sm ON shopping.id = sm.shopping_id AND sm.user_id = $1
+            LEFT OUTER JOIN household_member AS hm
+                ON shopping.household_id = hm.household_id AND hm.user_id = $1
+            WHERE has_access(shopping.user_id, shopping.household_id, $1) OR sm.id IS NOT NULL
+            GROUP BY shopping.id
+        ) AS lists
+        WHERE ($3::boolean IS NULL OR (ingredients > checked) = $3)
+        AND ($5::text IS NULL OR CASE
+            WHEN $4 = 'desc' THEN (sort_key, id) < ($5, $6)
+            ELSE (sort_key, id) > ($5, $6)
+        END)
+        ORDER BY
+            CASE WHEN $4 = 'desc' THEN sort_key END DESC,
+            CASE WHEN $4 = 'desc' THEN id END DESC,
+            sort_key, id
+        LIMIT $7
       "#,
-      claims.get_sub()
+      claims.get_sub(),
+      sort,
+      query.unchecked,
+      pagination.order().as_str(),
+      pagination.cursor_key(),
+      pagination.cursor_id(),
+      pagination.fetch_limit()
   )
   .fetch_all(&pool)
   .await
@@ -36,27 +98,40 @@ pub async fn get_all(
     "Failed getting shopping lists".to_string(),
     ))?;
 
-    Ok((
-        StatusCode::OK,
-        Json(
-            recipes
-                .into_iter()
-                .map(|record| GetAllRes {
-                    id: record.id,
-                    name: record.name,
-                    ingredients: record.ingredients.unwrap_or(0),
-                    checked: record.checked.unwrap_or(0),
-                })
-                .collect(),
-        ),
-    ))
+    let page = pagination
+        .page(sort, recipes, |record| (record.sort_key.clone(), record.id))
+        .map(|record| GetAllRes {
+            id: record.id,
+            name: record.name,
+            ingredients: record.ingredients,
+            checked: record.checked,
+            role: record.role,
+            household_id: record.household_id,
+        });
+
+    Ok((StatusCode::OK, Json(page)))
 }
 
 #[derive(Serialize)]
 pub struct GetRes {
     pub id: i32,
     pub name: String,
+    pub role: Role,
+    /// Ordered by category and then by the ingredient's sort, so the list follows the walk
+    /// through the store. The order of the requested store comes first.
     pub ingredients: Vec<Ingredient>,
+    pub categories: Vec<Category>,
+    /// Sum of the ingredients with a known price
+    #[serde(with = "quantity::option")]
+    pub estimated_cost: Option<Decimal>,
+}
+
+/// Shopping ingredients of a category, ingredients without category come last
+#[derive(Serialize)]
+pub struct Category {
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub ingredients: Vec<i32>,
 }
 
 #[derive(Serialize)]
@@ -64,46 +139,99 @@ pub struct Ingredient {
     pub id: i32,
     pub name: String,
     pub unit: String,
+    pub category_id: Option<i32>,
+    /// Store the ingredient is bought at, any store if `None`
+    pub store_id: Option<i32>,
     pub checked: bool,
+    /// Username of who checked the ingredient
+    pub checked_by: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub checked_at: Option<OffsetDateTime>,
+    /// Sum of all quantities, `None` if they can't be converted into each other.
+    /// Pantry stock is already subtracted if requested.
+    pub total: Option<Total>,
+    /// Pantry stock of the ingredient, only if requested
+    pub pantry: Option<Total>,
+    /// Cost of the total at the latest known price, prices of the requested store come first
+    #[serde(with = "quantity::option")]
+    pub cost: Option<Decimal>,
     pub quantities: Vec<Quantities>,
 }
 
+#[derive(Serialize)]
+pub struct Total {
+    #[serde(with = "quantity")]
+    pub quantity: Decimal,
+    pub unit: String,
+}
+
 #[derive(Serialize, Clone)]
 pub struct Quantities {
     pub id: i32,
     pub shopping_ingredient_id: i32,
-    pub quantity: i32,
+    #[serde(with = "quantity")]
+    pub quantity: Decimal,
+    pub unit_id: i32,
+    pub unit: String,
     pub recipe_id: Option<i32>,
     pub recipe_name: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct GetQuery {
+    /// Subtract pantry stock from the totals
+    #[serde(default)]
+    pub pantry: bool,
+    /// Only shopping ingredients to buy at this store, in its walking order
+    pub store: Option<i32>,
+}
+
 #[axum_macros::debug_handler]
 pub async fn get(
     claims: Claims,
     Path(id): Path<i32>,
+    Query(query): Query<GetQuery>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<(StatusCode, Json<GetRes>), (StatusCode, String)> {
     let default_err = get_default_err("Failed getting shopping list");
 
-    let shopping_list = sqlx::query!(
-        r#"SELECT id, name FROM shopping WHERE id = $1 AND user_id = $2"#,
+    let role = validate_shopping_id(
         id,
-        claims.get_sub()
+        claims.get_sub(),
+        Role::Viewer,
+        default_err.clone(),
+        &pool,
     )
-    .fetch_optional(&pool)
-    .await
-    .map_err(|_| default_err.clone())?
-    .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))?;
+    .await?;
+
+    if let Some(store_id) = query.store {
+        validate_store_id(store_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    let shopping_list = sqlx::query!(r#"SELECT id, name FROM shopping WHERE id = $1"#, id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| default_err.clone())?;
 
     let ingredients = sqlx::query!(
         r#"
-            SELECT si.id, si.checked, i.name, u.name AS unit
+            SELECT si.id, si.checked, cu.username AS "checked_by?", si.checked_at,
+            si.ingredient_id, i.name, i.unit_id, i.density, u.name AS unit,
+            si.store_id, c.id AS "category_id?", c.name AS "category?"
             FROM shopping_ingredient AS si
             JOIN ingredient AS i ON si.ingredient_id = i.id
             JOIN unit AS u ON i.unit_id = u.id
-            WHERE shopping_id = $1
+            LEFT JOIN "user" AS cu ON si.checked_by = cu.id
+            LEFT JOIN category AS c ON i.category_id = c.id AND has_access(c.user_id, c.household_id, $3)
+            LEFT JOIN store_category AS sc ON sc.store_id = $2 AND c.id = sc.category_id
+            LEFT JOIN store_ingredient AS sti ON sti.store_id = $2 AND i.id = sti.ingredient_id
+            WHERE shopping_id = $1 AND ($2::integer IS NULL OR si.store_id IS NULL OR si.store_id = $2)
+            ORDER BY sc.sort NULLS LAST, c.sort NULLS LAST, c.name, c.id,
+            sti.sort NULLS LAST, i.sort, i.name
         "#,
-        id
+        id,
+        query.store,
+        claims.get_sub()
     )
     .fetch_all(&pool)
     .await
@@ -114,8 +242,10 @@ pub async fn get(
     let quantities = sqlx::query_as!(
         Quantities,
         r#"
-            SELECT sq.id, sq.shopping_ingredient_id, sq.quantity, r.id AS recipe_id, r.name AS recipe_name
+            SELECT sq.id, sq.shopping_ingredient_id, sq.quantity, u.id AS unit_id, u.name AS unit,
+            r.id AS recipe_id, r.name AS recipe_name
             FROM shopping_quantity AS sq
+            JOIN unit AS u ON sq.unit_id = u.id
             LEFT JOIN recipe AS r ON sq.recipe_id = r.id
             WHERE shopping_ingredient_id = ANY($1)
         "#,
@@ -123,32 +253,123 @@ pub async fn get(
     )
     .fetch_all(&pool)
     .await
-    .map_err(|_| default_err )?;
+    .map_err(|_| default_err.clone())?;
+
+    let ingredient_ids: Vec<i32> = ingredients.iter().map(|i| i.ingredient_id).collect();
+
+    let pantry = match query.pantry {
+        true => {
+            get_pantry_stock(
+                &ingredient_ids,
+                claims.get_sub(),
+                default_err.clone(),
+                &pool,
+            )
+            .await?
+        }
+        false => Vec::new(),
+    };
 
-    let ingredients = ingredients
+    let prices = get_latest_prices(
+        &ingredient_ids,
+        claims.get_sub(),
+        query.store,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let units = get_shopping_units(id, default_err, &pool).await?;
+
+    let mut categories: Vec<Category> = Vec::new();
+    for ingredient in ingredients.iter() {
+        match categories.last_mut() {
+            Some(category) if category.id == ingredient.category_id => {
+                category.ingredients.push(ingredient.id)
+            }
+            _ => categories.push(Category {
+                id: ingredient.category_id,
+                name: ingredient.category.clone(),
+                ingredients: vec![ingredient.id],
+            }),
+        }
+    }
+
+    let ingredients: Vec<Ingredient> = ingredients
         .into_iter()
         .map(|i| {
-            let quantities = quantities
+            let quantities: Vec<Quantities> = quantities
                 .clone()
                 .into_iter()
                 .filter(|q| q.shopping_ingredient_id == i.id)
                 .collect();
-            Ingredient {
+            let unit = find_unit(i.unit_id, &units)?;
+            let mut total = sum(
+                quantities.iter().map(|q| (q.quantity, q.unit_id)),
+                unit,
+                i.density,
+                &units,
+            );
+            let stock = match query.pantry {
+                true => sum(
+                    pantry
+                        .iter()
+                        .filter(|p| p.ingredient_id == i.ingredient_id)
+                        .map(|p| (p.quantity, p.unit_id)),
+                    unit,
+                    i.density,
+                    &units,
+                ),
+                false => None,
+            };
+            if let (Some((quantity, unit)), Some((stock, stock_unit))) = (total, stock) {
+                if let Some(stock) = convert(stock, stock_unit, unit, i.density) {
+                    total = Some(normalize(
+                        (quantity - stock).max(Decimal::ZERO),
+                        unit,
+                        &units,
+                    ));
+                }
+            }
+            let cost = total.and_then(|(quantity, unit)| {
+                cost(quantity, unit, i.ingredient_id, i.density, &prices, &units)
+            });
+            let total = total.map(|(quantity, unit)| Total {
+                quantity,
+                unit: unit.name.clone(),
+            });
+            let pantry = stock.map(|(quantity, unit)| Total {
+                quantity,
+                unit: unit.name.clone(),
+            });
+            Ok(Ingredient {
                 id: i.id,
                 name: i.name,
                 unit: i.unit,
+                category_id: i.category_id,
+                store_id: i.store_id,
                 checked: i.checked,
+                checked_by: i.checked_by,
+                checked_at: i.checked_at,
+                total,
+                pantry,
+                cost,
                 quantities,
-            }
+            })
         })
-        .collect();
+        .collect::<Result<_, (StatusCode, String)>>()?;
+
+    let estimated_cost = total_cost(ingredients.iter().map(|i| i.cost));
 
     Ok((
         StatusCode::OK,
         Json(GetRes {
             id: shopping_list.id,
             name: shopping_list.name,
+            role,
             ingredients,
+            categories,
+            estimated_cost,
         }),
     ))
 }
@@ -156,6 +377,8 @@ pub async fn get(
 #[derive(Deserialize)]
 pub struct CreateReq {
     name: String,
+    /// Creates the list in a household instead of the personal library
+    household_id: Option<i32>,
 }
 
 #[axum_macros::debug_handler]
@@ -164,10 +387,22 @@ pub async fn create(
     extract::Json(payload): extract::Json<CreateReq>,
     Extension(pool): Extension<PgPool>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            get_default_err("Failed creating shopping list"),
+            &pool,
+        )
+        .await?;
+    }
+
     sqlx::query!(
-        r#"INSERT INTO shopping ( name, user_id ) VALUES ( $1, $2 )"#,
+        r#"INSERT INTO shopping ( name, user_id, household_id ) VALUES ( $1, $2, $3 )"#,
         payload.name,
-        claims.get_sub()
+        claims.get_sub(),
+        payload.household_id
     )
     .execute(&pool)
     .await
@@ -181,20 +416,65 @@ pub async fn create(
     Ok(StatusCode::CREATED)
 }
 
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating shopping list");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"UPDATE shopping SET name = $1 WHERE id = $2"#,
+        payload.name,
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
 #[axum_macros::debug_handler]
 pub async fn delete(
     claims: Claims,
     Path(id): Path<i32>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Owner,
+        get_default_err("Failed deleting shopping list"),
+        &pool,
+    )
+    .await?;
+
     sqlx::query!(
         r#"
             DELETE FROM shopping
-            WHERE id = $1 AND user_id = $2
+            WHERE id = $1
             RETURNING id
         "#,
-        id,
-        claims.get_sub()
+        id
     )
     .fetch_optional(&pool)
     .await
@@ -206,5 +486,7 @@ pub async fn delete(
     })?
     .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))?;
 
+    shopping_events.close(id);
+
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,54 @@
+use crate::api::{global::get_default_err, shopping::sync::MAX_OFFLINE};
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How often sync bookkeeping is pruned
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Prunes applied sync operations and removed shopping ingredients in the background, forever
+pub async fn run(pool: PgPool) {
+    let mut interval = tokio::time::interval(INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err((_, message)) = prune(&pool).await {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Deletes what's older than clients can stay offline. Lists remember the last pruned removal, so
+/// clients with an older sync token get the whole list instead of missing removals.
+async fn prune(pool: &PgPool) -> Result<(), (StatusCode, String)> {
+    let default_err = get_default_err("Failed pruning shopping list sync");
+    let cutoff = OffsetDateTime::now_utc() - MAX_OFFLINE;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            WITH pruned AS (
+                DELETE FROM shopping_ingredient_removed WHERE removed_at < $1
+                RETURNING shopping_id, version
+            )
+            UPDATE shopping AS s SET pruned_version = GREATEST(s.pruned_version, p.version)
+            FROM (SELECT shopping_id, max(version) AS version FROM pruned GROUP BY shopping_id) AS p
+            WHERE s.id = p.shopping_id
+        "#,
+        cutoff
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"DELETE FROM shopping_sync_operation WHERE created_at < $1"#,
+        cutoff
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)
+}
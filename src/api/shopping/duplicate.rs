@@ -0,0 +1,114 @@
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    shopping::service::{validate_shopping_id, Role},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct DuplicateReq {
+    /// Defaults to the name of the duplicated list
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: Option<String>,
+    /// Leaves out checked ingredients
+    #[serde(default)]
+    unchecked_only: bool,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateRes {
+    id: i32,
+}
+
+/// Copies a shopping list with its ingredients and quantities. The copy belongs to the user,
+/// in the list's household if they are a member of it. Ingredients and units the copy's users
+/// don't have access to are left out, like they couldn't have added them.
+#[axum_macros::debug_handler]
+pub async fn duplicate(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<DuplicateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<DuplicateRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed duplicating shopping list");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Viewer,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let shopping_id = sqlx::query!(
+        r#"
+            INSERT INTO shopping ( name, user_id, household_id )
+            SELECT COALESCE($2, s.name), $3, hm.household_id
+            FROM shopping AS s
+            LEFT JOIN household_member AS hm ON s.household_id = hm.household_id AND hm.user_id = $3
+            WHERE s.id = $1
+            RETURNING id
+        "#,
+        id,
+        payload.name,
+        claims.get_sub()
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .id;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO shopping_ingredient ( shopping_id, ingredient_id, checked, checked_by, checked_at, store_id )
+            SELECT $2, si.ingredient_id, si.checked, si.checked_by, si.checked_at, st.id
+            FROM shopping_ingredient AS si
+            JOIN ingredient AS i ON si.ingredient_id = i.id
+            LEFT JOIN store AS st ON si.store_id = st.id AND st.user_id IN (SELECT co_members($4))
+            WHERE si.shopping_id = $1 AND NOT ($3 AND si.checked)
+            AND EXISTS (
+                SELECT 1 FROM shopping_users($2) AS u WHERE has_access(i.user_id, i.household_id, u)
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM shopping_quantity AS sq
+                JOIN unit ON sq.unit_id = unit.id
+                WHERE sq.shopping_ingredient_id = si.id AND unit.user_id IS NOT NULL
+                AND unit.user_id NOT IN (SELECT co_members(u) FROM shopping_users($2) AS u)
+            )
+        "#,
+        id,
+        shopping_id,
+        payload.unchecked_only,
+        claims.get_sub()
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO shopping_quantity ( shopping_ingredient_id, recipe_id, quantity, unit_id )
+            SELECT copy.id, sq.recipe_id, sq.quantity, sq.unit_id
+            FROM shopping_quantity AS sq
+            JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+            JOIN shopping_ingredient AS copy
+                ON copy.shopping_id = $2 AND copy.ingredient_id = si.ingredient_id
+            WHERE si.shopping_id = $1
+        "#,
+        id,
+        shopping_id
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok((StatusCode::CREATED, Json(DuplicateRes { id: shopping_id })))
+}
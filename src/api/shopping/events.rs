@@ -0,0 +1,191 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    shopping::service::{validate_shopping_id, Role},
+};
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::sse::{self, KeepAlive, Sse},
+    Extension,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use time::OffsetDateTime;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+/// Change of a shopping list, ids are the ids of shopping ingredients
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    IngredientAdded { id: i32 },
+    IngredientChecked { id: i32, checked: bool },
+    IngredientRemoved { id: i32 },
+    StoreAssigned { id: i32, store_id: Option<i32> },
+    QuantityChanged { id: i32 },
+}
+
+impl Event {
+    /// Id of the changed shopping ingredient
+    pub fn id(&self) -> i32 {
+        match *self {
+            Event::IngredientAdded { id }
+            | Event::IngredientChecked { id, .. }
+            | Event::IngredientRemoved { id }
+            | Event::StoreAssigned { id, .. }
+            | Event::QuantityChanged { id } => id,
+        }
+    }
+}
+
+/// Events a follower can buffer before it misses some
+const CAPACITY: usize = 256;
+
+/// Publishes changes of shopping lists to everyone following them, every list has its own
+/// channel so a busy list doesn't make followers of other lists miss events
+#[derive(Clone, Default)]
+pub struct ShoppingEvents {
+    senders: Arc<Mutex<HashMap<i32, broadcast::Sender<Event>>>>,
+}
+
+impl ShoppingEvents {
+    pub fn publish(&self, shopping_id: i32, events: impl IntoIterator<Item = Event>) {
+        let mut senders = self.senders.lock().unwrap();
+        if let Some(sender) = senders.get(&shopping_id) {
+            for event in events {
+                // Sending only fails if nobody follows the list anymore
+                if sender.send(event).is_err() {
+                    senders.remove(&shopping_id);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Ends the streams of everyone following a deleted list
+    pub fn close(&self, shopping_id: i32) {
+        self.senders.lock().unwrap().remove(&shopping_id);
+    }
+
+    fn subscribe(&self, shopping_id: i32) -> broadcast::Receiver<Event> {
+        self.senders
+            .lock()
+            .unwrap()
+            .entry(shopping_id)
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Forgets the list's channel once its last follower is gone
+    fn unsubscribe(&self, shopping_id: i32, receiver: broadcast::Receiver<Event>) {
+        let mut senders = self.senders.lock().unwrap();
+        drop(receiver);
+        if let Some(sender) = senders.get(&shopping_id) {
+            if sender.receiver_count() == 0 {
+                senders.remove(&shopping_id);
+            }
+        }
+    }
+}
+
+/// Sent instead of the events a slow client missed, it has to reload the list
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Resync {
+    Resync,
+}
+
+#[axum_macros::debug_handler]
+pub async fn events(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, (StatusCode, String)> {
+    let default_err = get_default_err("Failed following shopping list");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Viewer,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut receiver = shopping_events.subscribe(id);
+    let (sender, stream) = mpsc::channel(CAPACITY);
+
+    // The stream ends when the token does, clients have to follow again with a new one
+    let now = OffsetDateTime::now_utc().unix_timestamp() as u64;
+    let expires_in = Duration::from_secs(claims.get_exp().saturating_sub(now));
+
+    // Forwards the list's events as long as the client is connected and still has access
+    tokio::spawn(async move {
+        forward(id, &claims, expires_in, &mut receiver, sender, &pool).await;
+        shopping_events.unsubscribe(id, receiver);
+    });
+
+    Ok(Sse::new(ReceiverStream::new(stream)).keep_alive(KeepAlive::default()))
+}
+
+async fn forward(
+    id: i32,
+    claims: &Claims,
+    expires_in: Duration,
+    receiver: &mut broadcast::Receiver<Event>,
+    sender: mpsc::Sender<Result<sse::Event, Infallible>>,
+    pool: &PgPool,
+) {
+    let default_err = get_default_err("Failed following shopping list");
+    let expired = tokio::time::sleep(expires_in);
+    tokio::pin!(expired);
+
+    loop {
+        let mut messages = tokio::select! {
+            _ = sender.closed() => return,
+            _ = &mut expired => return,
+            message = receiver.recv() => vec![message],
+        };
+        while let Ok(event) = receiver.try_recv() {
+            messages.push(Ok(event));
+        }
+
+        // Members can be removed while following, the stream ends once they are
+        if validate_shopping_id(
+            id,
+            claims.get_sub(),
+            Role::Viewer,
+            default_err.clone(),
+            pool,
+        )
+        .await
+        .is_err()
+        {
+            return;
+        }
+
+        for message in messages {
+            let event = match message {
+                Ok(event) => sse::Event::default().json_data(event),
+                Err(RecvError::Lagged(_)) => sse::Event::default().json_data(Resync::Resync),
+                Err(RecvError::Closed) => return,
+            };
+            if let Ok(event) = event {
+                if sender.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
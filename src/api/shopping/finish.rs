@@ -0,0 +1,82 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    pantry::service::add_pantry_quantity,
+    shopping::{
+        events::{Event, ShoppingEvents},
+        service::{validate_shopping_id, Role},
+    },
+};
+use axum::{extract::Path, http::StatusCode, Extension};
+use sqlx::PgPool;
+
+/// Moves the checked ingredients of a shopping list into the pantry of the user, or of the
+/// ingredient's owner if the user can't access the ingredient
+#[axum_macros::debug_handler]
+pub async fn finish(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed finishing shopping trip");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT si.ingredient_id, sq.quantity, sq.unit_id,
+            CASE WHEN has_access(i.user_id, i.household_id, $2) THEN $2 ELSE i.user_id END AS "user_id!"
+            FROM shopping_quantity AS sq
+            JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+            JOIN ingredient AS i ON si.ingredient_id = i.id
+            WHERE si.shopping_id = $1 AND si.checked
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    for quantity in quantities.into_iter() {
+        add_pantry_quantity(
+            quantity.ingredient_id,
+            quantity.quantity,
+            Some(quantity.unit_id),
+            None,
+            quantity.user_id,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+    }
+
+    let removed = sqlx::query!(
+        r#"DELETE FROM shopping_ingredient WHERE shopping_id = $1 AND checked RETURNING id"#,
+        id
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(
+        id,
+        removed
+            .into_iter()
+            .map(|record| Event::IngredientRemoved { id: record.id }),
+    );
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,190 @@
+use crate::api::{
+    auth::Claims,
+    date,
+    global::{get_default_err, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+    meal_plans::api::MAX_DAYS,
+    shopping::{
+        events::ShoppingEvents,
+        service::{add_shopping_recipe, validate_recipe_id, validate_shopping_id, Role},
+    },
+};
+use axum::{http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::Date;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct GenerateReq {
+    /// List to fill, a new list is created if omitted
+    shopping_id: Option<i32>,
+    /// Name of the new list
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: Option<String>,
+    /// Household of the new list
+    household_id: Option<i32>,
+    /// Adds the meals planned from `from` to `to`
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    from: Option<Date>,
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    to: Option<Date>,
+    /// Adds these recipes instead of planned meals
+    #[validate]
+    recipes: Option<Vec<Recipe>>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct Recipe {
+    id: i32,
+    /// Defaults to the servings of the recipe
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    servings: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct GenerateRes {
+    id: i32,
+}
+
+#[axum_macros::debug_handler]
+pub async fn generate(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<GenerateReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<(StatusCode, Json<GenerateRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed generating shopping list");
+
+    if payload.shopping_id.is_some() && (payload.name.is_some() || payload.household_id.is_some()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Name and household can only be set for a new shopping list".to_string(),
+        ));
+    }
+
+    let recipes: Vec<(i32, Option<i32>)> = match (payload.recipes, payload.from, payload.to) {
+        (Some(recipes), None, None) => {
+            for recipe in recipes.iter() {
+                validate_recipe_id(recipe.id, claims.get_sub(), default_err.clone(), &pool).await?;
+            }
+            recipes.into_iter().map(|r| (r.id, r.servings)).collect()
+        }
+        (None, Some(from), Some(to)) => {
+            if to < from {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "End date can't be before start date".to_string(),
+                ));
+            }
+            if (to - from).whole_days() >= MAX_DAYS {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Date range can't exceed {} days", MAX_DAYS),
+                ));
+            }
+
+            let meals = sqlx::query!(
+                r#"
+                    SELECT recipe_id, servings FROM meal_plan
+                    WHERE user_id = $1 AND date BETWEEN $2 AND $3
+                    ORDER BY date, id
+                "#,
+                claims.get_sub(),
+                from,
+                to
+            )
+            .fetch_all(&pool)
+            .await
+            .map_err(|_| default_err.clone())?;
+
+            if meals.is_empty() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "No meals planned in this date range".to_string(),
+                ));
+            }
+            meals
+                .into_iter()
+                .map(|meal| (meal.recipe_id, Some(meal.servings)))
+                .collect()
+        }
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Either a date range or recipes are required".to_string(),
+            ))
+        }
+    };
+
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    if let Some(shopping_id) = payload.shopping_id {
+        validate_shopping_id(
+            shopping_id,
+            claims.get_sub(),
+            Role::Editor,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    // Either the whole list is filled or nothing is changed
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let shopping_id = match (payload.shopping_id, payload.name) {
+        (Some(shopping_id), _) => shopping_id,
+        (None, Some(name)) => {
+            sqlx::query!(
+                r#"
+                    INSERT INTO shopping ( name, user_id, household_id )
+                    VALUES ( $1, $2, $3 )
+                    RETURNING id
+                "#,
+                name,
+                claims.get_sub(),
+                payload.household_id
+            )
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|_| default_err.clone())?
+            .id
+        }
+        (None, None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Name is required for a new shopping list".to_string(),
+            ))
+        }
+    };
+
+    let mut events = Vec::new();
+    for (recipe_id, servings) in recipes.into_iter() {
+        let recipe_events = add_shopping_recipe(
+            recipe_id,
+            servings,
+            None,
+            shopping_id,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+        events.extend(recipe_events);
+    }
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(shopping_id, events);
+
+    Ok((StatusCode::CREATED, Json(GenerateRes { id: shopping_id })))
+}
@@ -1,17 +1,43 @@
 use crate::api::{
     auth::Claims,
+    flags::service::{get_allergen_warnings, AllergenWarning},
     global::{get_default_err, ValidatedJson},
-    shopping::service::{add_shopping_quantity, validate_shopping_id},
+    shopping::{
+        events::{Event, ShoppingEvents},
+        service::{
+            add_shopping_quantity, set_checked, validate_shopping_id,
+            validate_shopping_ingredient_ids, NewQuantity, Role,
+        },
+    },
+    stores::service::validate_store_id,
+    units::quantity::{self, validate_quantity},
 };
-use axum::{extract::Path, http::StatusCode, Extension};
-use serde::Deserialize;
+use axum::{
+    extract::{self, Path},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use time::OffsetDateTime;
 use validator::Validate;
 
 #[derive(Deserialize, Validate)]
 pub struct AddIngredientReq {
-    #[validate(range(min = 1, message = "Quantity has to be at least 1"))]
-    quantity: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    quantity: Decimal,
+    unit_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct AddRes {
+    /// Added ingredients containing allergens
+    pub warnings: Vec<AllergenWarning>,
 }
 
 #[axum_macros::debug_handler]
@@ -20,65 +46,214 @@ pub async fn add_ingredient(
     Path((id, ingredient_id)): Path<(i32, i32)>,
     ValidatedJson(payload): ValidatedJson<AddIngredientReq>,
     Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, (StatusCode, String)> {
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<(StatusCode, Json<AddRes>), (StatusCode, String)> {
     let default_err = get_default_err("Failed adding ingredient to shopping list");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
-
-    add_shopping_quantity(
-        ingredient_id,
-        payload.quantity,
+    validate_shopping_id(
+        id,
         claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut conn = pool.acquire().await.map_err(|_| default_err.clone())?;
+
+    let event = add_shopping_quantity(
+        NewQuantity {
+            ingredient_id,
+            quantity: payload.quantity,
+            unit_id: payload.unit_id,
+        },
         id,
         None,
         default_err.clone(),
-        &pool,
+        &mut conn,
     )
     .await?;
 
-    Ok(StatusCode::CREATED)
+    shopping_events.publish(id, [event]);
+
+    let warnings = get_allergen_warnings(&[ingredient_id], default_err, &mut conn).await?;
+
+    Ok((StatusCode::CREATED, Json(AddRes { warnings })))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CheckReq {
+    checked: bool,
 }
 
 #[axum_macros::debug_handler]
 pub async fn check_ingredient(
     claims: Claims,
     Path((id, ingredient_id)): Path<(i32, i32)>,
+    ValidatedJson(payload): ValidatedJson<CheckReq>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed checking shopping ingredient");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    validate_shopping_ingredient_ids(&[ingredient_id], id, default_err.clone(), &pool).await?;
 
-    sqlx::query!(
+    let changed = set_checked(
+        id,
+        Some(&[ingredient_id]),
+        payload.checked,
+        claims.get_sub(),
+        OffsetDateTime::now_utc(),
+        default_err,
+        &pool,
+    )
+    .await?;
+
+    publish_checked(&shopping_events, id, changed, payload.checked);
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CheckAllReq {
+    /// Shopping ingredients to check or uncheck, the whole list if omitted
+    ids: Option<Vec<i32>>,
+    checked: bool,
+}
+
+#[axum_macros::debug_handler]
+pub async fn check_ingredients(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<CheckAllReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed checking shopping ingredients");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    if let Some(ref ids) = payload.ids {
+        validate_shopping_ingredient_ids(ids, id, default_err.clone(), &pool).await?;
+    }
+
+    let changed = set_checked(
+        id,
+        payload.ids.as_deref(),
+        payload.checked,
+        claims.get_sub(),
+        OffsetDateTime::now_utc(),
+        default_err,
+        &pool,
+    )
+    .await?;
+
+    publish_checked(&shopping_events, id, changed, payload.checked);
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct AssignStoreReq {
+    ids: Vec<i32>,
+    /// `null` buys the shopping ingredients at any store
+    store_id: Option<i32>,
+}
+
+/// Splits a list across stores, the list of a store contains its own shopping ingredients and
+/// those without store
+#[axum_macros::debug_handler]
+pub async fn assign_store(
+    claims: Claims,
+    Path(id): Path<i32>,
+    extract::Json(payload): extract::Json<AssignStoreReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed assigning shopping ingredients to store");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    validate_shopping_ingredient_ids(&payload.ids, id, default_err.clone(), &pool).await?;
+
+    if let Some(store_id) = payload.store_id {
+        validate_store_id(store_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    let changed = sqlx::query!(
         r#"
-            UPDATE shopping_ingredient
-            SET checked = NOT checked
-            WHERE id = $1 AND shopping_id = $2
+            UPDATE shopping_ingredient SET store_id = $3
+            WHERE shopping_id = $1 AND id = ANY($2) AND store_id IS DISTINCT FROM $3
             RETURNING id
         "#,
-        ingredient_id,
         id,
+        &payload.ids,
+        payload.store_id
     )
-    .fetch_optional(&pool)
+    .fetch_all(&pool)
     .await
-    .map_err(|_| default_err)?
-    .ok_or((
-        StatusCode::NOT_FOUND,
-        "Shopping ingredient not found".to_string(),
-    ))?;
+    .map_err(|_| default_err)?;
+
+    shopping_events.publish(
+        id,
+        changed.into_iter().map(|record| Event::StoreAssigned {
+            id: record.id,
+            store_id: payload.store_id,
+        }),
+    );
 
     Ok(StatusCode::OK)
 }
 
+fn publish_checked(shopping_events: &ShoppingEvents, id: i32, changed: Vec<i32>, checked: bool) {
+    shopping_events.publish(
+        id,
+        changed
+            .into_iter()
+            .map(|id| Event::IngredientChecked { id, checked }),
+    );
+}
+
 #[axum_macros::debug_handler]
 pub async fn delete_ingredient(
     claims: Claims,
     Path((id, ingredient_id)): Path<(i32, i32)>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed deleting shopping ingredient");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
     let shopping_ingredient = sqlx::query!(
         r#"
@@ -106,5 +281,12 @@ pub async fn delete_ingredient(
     .await
     .map_err(|_| default_err)?;
 
+    shopping_events.publish(
+        id,
+        [Event::IngredientRemoved {
+            id: shopping_ingredient.id,
+        }],
+    );
+
     Ok(StatusCode::OK)
 }
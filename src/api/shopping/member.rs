@@ -0,0 +1,233 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    shopping::service::{validate_shopping_id, Role},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct Member {
+    user_id: Uuid,
+    username: String,
+    alias: String,
+    role: Role,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_members(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<Member>>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting shopping list members");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Viewer,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    // Same roles as `validate_shopping_id`, household members included
+    let members = sqlx::query!(
+        r#"
+            SELECT u.id, u.username, u.alias, CASE
+                WHEN s.household_id IS NULL AND s.user_id = u.id THEN 'owner'
+                WHEN hm.role = 'admin' THEN 'owner'
+                WHEN hm.role = 'member' THEN 'editor'
+                ELSE sm.role
+            END AS "role!"
+            FROM shopping AS s
+            CROSS JOIN shopping_users(s.id) AS su(user_id)
+            JOIN "user" AS u ON su.user_id = u.id
+            LEFT JOIN household_member AS hm ON s.household_id = hm.household_id AND hm.user_id = u.id
+            LEFT JOIN shopping_member AS sm ON s.id = sm.shopping_id AND sm.user_id = u.id
+            WHERE s.id = $1 AND (has_access(s.user_id, s.household_id, u.id) OR sm.id IS NOT NULL)
+            ORDER BY u.username
+        "#,
+        id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let members = members
+        .into_iter()
+        .map(|record| {
+            Ok(Member {
+                user_id: record.id,
+                username: record.username,
+                alias: record.alias,
+                role: Role::from_str(&record.role).map_err(|_| default_err.clone())?,
+            })
+        })
+        .collect::<Result<_, (StatusCode, String)>>()?;
+
+    Ok((StatusCode::OK, Json(members)))
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberReq {
+    username: String,
+    role: Role,
+}
+
+/// Invites a user by username, only the owner can manage members
+#[axum_macros::debug_handler]
+pub async fn add_member(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<AddMemberReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed adding shopping list member");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Owner,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    if payload.role == Role::Owner {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Members can only be editors or viewers".to_string(),
+        ));
+    }
+
+    let user = sqlx::query!(
+        r#"SELECT id FROM "user" WHERE username = $1"#,
+        payload.username.to_lowercase()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    // The owner and household members already have access without being members
+    let shopping = sqlx::query!(
+        r#"SELECT has_access(user_id, household_id, $2) AS "has_access!" FROM shopping WHERE id = $1"#,
+        id,
+        user.id
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    if shopping.has_access {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "User already owns this shopping list or shares its household".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+            INSERT INTO shopping_member ( shopping_id, user_id, role )
+            VALUES ( $1, $2, $3 )
+            ON CONFLICT DO NOTHING
+            RETURNING id
+        "#,
+        id,
+        user.id,
+        payload.role.as_str()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((
+        StatusCode::CONFLICT,
+        "User is already a member of this shopping list".to_string(),
+    ))?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMemberReq {
+    role: Role,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, Uuid)>,
+    Json(payload): Json<UpdateMemberReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating shopping list member");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Owner,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    if payload.role == Role::Owner {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Members can only be editors or viewers".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+            UPDATE shopping_member SET role = $1
+            WHERE shopping_id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        payload.role.as_str(),
+        id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes a member, members can also remove themselves to leave a list
+#[axum_macros::debug_handler]
+pub async fn delete_member(
+    claims: Claims,
+    Path((id, user_id)): Path<(i32, Uuid)>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed removing shopping list member");
+
+    let required = match user_id == claims.get_sub() {
+        true => Role::Viewer,
+        false => Role::Owner,
+    };
+    validate_shopping_id(id, claims.get_sub(), required, default_err.clone(), &pool).await?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM shopping_member
+            WHERE shopping_id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
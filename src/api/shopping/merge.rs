@@ -0,0 +1,105 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    shopping::{
+        events::ShoppingEvents,
+        service::{add_shopping_quantity, validate_shopping_id, NewQuantity, Role},
+    },
+};
+use axum::{
+    extract::{self, Path},
+    http::StatusCode,
+    Extension,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct MergeReq {
+    /// List merged into this one, it's deleted afterwards
+    shopping_id: i32,
+}
+
+/// Adds all quantities of another list to this one and deletes the other list.
+/// Quantities of the same ingredient and recipe are combined.
+#[axum_macros::debug_handler]
+pub async fn merge(
+    claims: Claims,
+    Path(id): Path<i32>,
+    extract::Json(payload): extract::Json<MergeReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed merging shopping lists");
+
+    if payload.shopping_id == id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Can't merge a shopping list into itself".to_string(),
+        ));
+    }
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    // The merged list gets deleted
+    validate_shopping_id(
+        payload.shopping_id,
+        claims.get_sub(),
+        Role::Owner,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT si.ingredient_id, sq.recipe_id, sq.quantity, sq.unit_id
+            FROM shopping_quantity AS sq
+            JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+            WHERE si.shopping_id = $1
+            ORDER BY sq.id
+        "#,
+        payload.shopping_id
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let mut events = Vec::new();
+    for quantity in quantities.into_iter() {
+        let event = add_shopping_quantity(
+            NewQuantity {
+                ingredient_id: quantity.ingredient_id,
+                quantity: quantity.quantity,
+                unit_id: Some(quantity.unit_id),
+            },
+            id,
+            quantity.recipe_id,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+        events.push(event);
+    }
+
+    sqlx::query!(r#"DELETE FROM shopping WHERE id = $1"#, payload.shopping_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(id, events);
+    shopping_events.close(payload.shopping_id);
+
+    Ok(StatusCode::OK)
+}
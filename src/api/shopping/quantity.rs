@@ -1,35 +1,54 @@
-use crate::api::{auth::Claims, global::get_default_err, shopping::service::validate_shopping_id};
-use axum::{
-    extract::{self, Path},
-    http::StatusCode,
-    Extension,
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    shopping::{
+        events::{Event, ShoppingEvents},
+        service::{validate_shopping_id, Role},
+    },
+    units::quantity::{self, validate_quantity},
 };
+use axum::{extract::Path, http::StatusCode, Extension};
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use sqlx::PgPool;
+use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateQuantityReq {
-    pub quantity: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    pub quantity: Decimal,
 }
 
 #[axum_macros::debug_handler]
 pub async fn update_quantity(
     claims: Claims,
     Path((id, ingredient_id)): Path<(i32, i32)>,
-    extract::Json(payload): extract::Json<UpdateQuantityReq>,
+    ValidatedJson(payload): ValidatedJson<UpdateQuantityReq>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed updating shopping ingredient");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
-    sqlx::query!(
+    let updated = sqlx::query!(
         r#"
             UPDATE shopping_quantity AS sq
             SET quantity = $1
             FROM shopping_ingredient AS si
             WHERE sq.shopping_ingredient_id = si.id AND sq.id = $2 AND si.shopping_id = $3
-            RETURNING sq.id
+            RETURNING sq.shopping_ingredient_id
         "#,
         payload.quantity,
         ingredient_id,
@@ -43,6 +62,13 @@ pub async fn update_quantity(
         "Shopping quantity not found".to_string(),
     ))?;
 
+    shopping_events.publish(
+        id,
+        [Event::QuantityChanged {
+            id: updated.shopping_ingredient_id,
+        }],
+    );
+
     Ok(StatusCode::OK)
 }
 
@@ -51,14 +77,22 @@ pub async fn delete_quantity(
     claims: Claims,
     Path((id, quantity_id)): Path<(i32, i32)>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed deleting shopping quantity");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
     let shopping_ingredient = sqlx::query!(
         r#"
-          SELECT sq.id, COUNT(*) AS quantities
+          SELECT sq.id, sq.shopping_ingredient_id, COUNT(*) AS quantities
           FROM shopping_quantity AS sq
           JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
           JOIN shopping_quantity AS sq2 ON si.id = sq2.shopping_ingredient_id
@@ -92,15 +126,25 @@ pub async fn delete_quantity(
         "Shopping quantity not found".to_string(),
     ))?;
 
-    if shopping_ingredient.quantities.unwrap_or(0) <= 1 {
+    let event = if shopping_ingredient.quantities.unwrap_or(0) <= 1 {
         sqlx::query!(
             r#"DELETE FROM shopping_ingredient WHERE id = $1"#,
-            shopping_ingredient.id
+            shopping_ingredient.shopping_ingredient_id
         )
         .execute(&pool)
         .await
         .map_err(|_| default_err)?;
-    }
+
+        Event::IngredientRemoved {
+            id: shopping_ingredient.shopping_ingredient_id,
+        }
+    } else {
+        Event::QuantityChanged {
+            id: shopping_ingredient.shopping_ingredient_id,
+        }
+    };
+
+    shopping_events.publish(id, [event]);
 
     Ok(StatusCode::OK)
 }
@@ -1,24 +1,42 @@
 use crate::api::{
     auth::Claims,
+    flags::service::get_allergen_warnings,
     global::{get_default_err, ValidatedJson},
-    shopping::service::{add_shopping_quantity, validate_recipe_id, validate_shopping_id},
+    shopping::{
+        events::{Event, ShoppingEvents},
+        ingredient::AddRes,
+        service::{
+            add_shopping_recipe, validate_recipe_id, validate_shopping_id, NewQuantity, Role,
+        },
+    },
+    units::quantity::{self, validate_quantity},
 };
-use axum::{extract::Path, http::StatusCode, Extension};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use sqlx::PgPool;
 use validator::Validate;
 
 #[derive(Deserialize, Validate)]
 pub struct AddRecipeReq {
+    /// Servings to scale the quantities to, based on the servings of the recipe
+    #[validate(range(min = 1, message = "Servings have to be at least 1"))]
+    servings: Option<i32>,
+    /// Quantities for the recipe's servings, all ingredients of the recipe are added if omitted
     #[validate]
-    ingredients: Vec<Ingredient>,
+    ingredients: Option<Vec<Ingredient>>,
 }
 
 #[derive(Deserialize, Validate)]
 pub struct Ingredient {
     id: i32,
-    #[validate(range(min = 1, message = "Quantity has to be at least 1"))]
-    quantity: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    quantity: Decimal,
+    unit_id: Option<i32>,
 }
 
 #[axum_macros::debug_handler]
@@ -27,27 +45,63 @@ pub async fn add_recipe(
     Path((id, recipe_id)): Path<(i32, i32)>,
     ValidatedJson(payload): ValidatedJson<AddRecipeReq>,
     Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, (StatusCode, String)> {
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<(StatusCode, Json<AddRes>), (StatusCode, String)> {
     let default_err = get_default_err("Failed adding recipe to shopping list");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
     validate_recipe_id(recipe_id, claims.get_sub(), default_err.clone(), &pool).await?;
 
-    for ingredient in payload.ingredients.into_iter() {
-        add_shopping_quantity(
-            ingredient.id,
-            ingredient.quantity,
-            claims.get_sub(),
-            id,
-            Some(recipe_id),
-            default_err.clone(),
-            &pool,
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let ingredient_ids: Vec<i32> = match payload.ingredients {
+        Some(ref ingredients) => ingredients.iter().map(|i| i.id).collect(),
+        None => sqlx::query!(
+            r#"SELECT ingredient_id FROM recipe_quantity WHERE recipe_id = $1"#,
+            recipe_id
         )
-        .await?;
-    }
+        .fetch_all(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?
+        .into_iter()
+        .map(|record| record.ingredient_id)
+        .collect(),
+    };
+
+    let events = add_shopping_recipe(
+        recipe_id,
+        payload.servings,
+        payload.ingredients.map(|ingredients| {
+            ingredients
+                .into_iter()
+                .map(|ingredient| NewQuantity {
+                    ingredient_id: ingredient.id,
+                    quantity: ingredient.quantity,
+                    unit_id: ingredient.unit_id,
+                })
+                .collect()
+        }),
+        id,
+        default_err.clone(),
+        &mut tx,
+    )
+    .await?;
+
+    let warnings = get_allergen_warnings(&ingredient_ids, default_err.clone(), &mut tx).await?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(id, events);
 
-    Ok(StatusCode::CREATED)
+    Ok((StatusCode::CREATED, Json(AddRes { warnings })))
 }
 
 #[axum_macros::debug_handler]
@@ -55,12 +109,18 @@ pub async fn delete_recipe(
     claims: Claims,
     Path((id, recipe_id)): Path<(i32, i32)>,
     Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let default_err = get_default_err("Failed deleting shopping recipe");
 
-    validate_shopping_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
-
-    validate_recipe_id(recipe_id, claims.get_sub(), default_err.clone(), &pool).await?;
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
 
     let shopping_quantities = sqlx::query!(
         r#"
@@ -112,5 +172,20 @@ pub async fn delete_recipe(
     .await
     .map_err(|_| default_err)?;
 
+    let mut changed: Vec<i32> = shopping_quantities
+        .iter()
+        .map(|sq| sq.shopping_ingredient_id)
+        .filter(|id| !si_ids.contains(id))
+        .collect();
+    changed.dedup();
+
+    shopping_events.publish(
+        id,
+        si_ids
+            .iter()
+            .map(|&id| Event::IngredientRemoved { id })
+            .chain(changed.into_iter().map(|id| Event::QuantityChanged { id })),
+    );
+
     Ok(StatusCode::OK)
 }
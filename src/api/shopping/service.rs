@@ -1,23 +1,119 @@
+use super::events::Event;
+use crate::api::units::{
+    quantity::check_bounds,
+    service::{check_unit, convert, find_unit, scale_quantity, Unit},
+};
 use axum::http::StatusCode;
-use sqlx::PgPool;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::str::FromStr;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
+/// Access of a user to a shopping list. The owner is the user who created it or, for lists of a
+/// household, its admins. Other household members are editors.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Makes sure the user has at least `role` on the shopping list and returns the user's role
 pub async fn validate_shopping_id(
     shopping_id: i32,
     user_id: Uuid,
+    role: Role,
     default_err: (StatusCode, String),
     pool: &PgPool,
-) -> Result<(), (StatusCode, String)> {
-    sqlx::query!(
-        r#"SELECT * FROM shopping WHERE id = $1 AND user_id = $2"#,
+) -> Result<Role, (StatusCode, String)> {
+    let member = sqlx::query!(
+        r#"
+            SELECT CASE
+                WHEN s.household_id IS NULL AND s.user_id = $2 THEN 'owner'
+                WHEN hm.role = 'admin' THEN 'owner'
+                WHEN hm.role = 'member' THEN 'editor'
+                ELSE sm.role
+            END AS "role!"
+            FROM shopping AS s
+            LEFT JOIN household_member AS hm ON s.household_id = hm.household_id AND hm.user_id = $2
+            LEFT JOIN shopping_member AS sm ON s.id = sm.shopping_id AND sm.user_id = $2
+            WHERE s.id = $1 AND (has_access(s.user_id, s.household_id, $2) OR sm.id IS NOT NULL)
+        "#,
         shopping_id,
         user_id
     )
     .fetch_optional(pool)
     .await
-    .map_err(|_| default_err)?
-    .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))
-    .map(|_| ())
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))?;
+
+    let member_role = Role::from_str(&member.role).map_err(|_| default_err)?;
+    if member_role < role {
+        return Err((
+            StatusCode::FORBIDDEN,
+            match role {
+                Role::Owner => "Only the owner of the shopping list can do this",
+                _ => "Viewers can't change the shopping list",
+            }
+            .to_string(),
+        ));
+    }
+    Ok(member_role)
+}
+
+/// Global units, the custom units available to anyone with access and custom units still used on
+/// the list
+pub async fn get_shopping_units(
+    shopping_id: i32,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Unit>, (StatusCode, String)> {
+    sqlx::query_as!(
+        Unit,
+        r#"
+            SELECT id, name, family, factor, user_id FROM unit
+            WHERE user_id IS NULL
+            OR user_id IN (SELECT co_members(u) FROM shopping_users($1) AS u)
+            OR id IN (
+                SELECT sq.unit_id FROM shopping_quantity AS sq
+                JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+                WHERE si.shopping_id = $1
+                UNION SELECT i.unit_id FROM shopping_ingredient AS si
+                JOIN ingredient AS i ON si.ingredient_id = i.id
+                WHERE si.shopping_id = $1
+            )
+        "#,
+        shopping_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
 }
 
 pub async fn validate_recipe_id(
@@ -27,7 +123,7 @@ pub async fn validate_recipe_id(
     pool: &PgPool,
 ) -> Result<(), (StatusCode, String)> {
     sqlx::query!(
-        r#"SELECT * FROM recipe WHERE id = $1 AND user_id = $2"#,
+        r#"SELECT id FROM recipe WHERE id = $1 AND has_access(user_id, household_id, $2)"#,
         recipe_id,
         user_id
     )
@@ -38,25 +134,103 @@ pub async fn validate_recipe_id(
     .map(|_| ())
 }
 
+/// Makes sure all shopping ingredients are on the list
+pub async fn validate_shopping_ingredient_ids(
+    ids: &[i32],
+    shopping_id: i32,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<(), (StatusCode, String)> {
+    let existing: Vec<i32> = sqlx::query!(
+        r#"SELECT id FROM shopping_ingredient WHERE id = ANY($1) AND shopping_id = $2"#,
+        ids,
+        shopping_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)?
+    .into_iter()
+    .map(|record| record.id)
+    .collect();
+
+    match ids.iter().find(|id| !existing.contains(id)) {
+        Some(id) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Shopping ingredient with id {} not found", id),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Sets the check state of shopping ingredients, all of the list if `ids` is `None`.
+/// Ingredients already in that state are left untouched, so they keep who checked them and when.
+/// Returns the ids of the changed ingredients.
+pub async fn set_checked(
+    shopping_id: i32,
+    ids: Option<&[i32]>,
+    checked: bool,
+    user_id: Uuid,
+    checked_at: OffsetDateTime,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<i32>, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            UPDATE shopping_ingredient
+            SET checked = $3,
+            checked_by = CASE WHEN $3 THEN $4::uuid END,
+            checked_at = CASE WHEN $3 THEN $5::timestamptz END
+            WHERE shopping_id = $1 AND ($2::integer[] IS NULL OR id = ANY($2)) AND checked <> $3
+            RETURNING id
+        "#,
+        shopping_id,
+        ids,
+        checked,
+        user_id,
+        checked_at
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
+    .map(|records| records.into_iter().map(|record| record.id).collect())
+}
+
 pub struct ShoppingIngredientId {
     id: i32,
 }
 
+pub struct NewQuantity {
+    pub ingredient_id: i32,
+    pub quantity: Decimal,
+    /// Defaults to the unit of the ingredient
+    pub unit_id: Option<i32>,
+}
+
+/// Adds a quantity of an ingredient available to anyone with access to the list
 pub async fn add_shopping_quantity(
-    ingredient_id: i32,
-    ingredient_quantity: i32,
-    user_id: Uuid,
+    new_quantity: NewQuantity,
     shopping_id: i32,
     recipe_id: Option<i32>,
     default_err: (StatusCode, String),
-    pool: &PgPool,
-) -> Result<(), (StatusCode, String)> {
-    sqlx::query!(
-        r#"SELECT * FROM ingredient WHERE id = $1 AND user_id = $2"#,
+    conn: &mut PgConnection,
+) -> Result<Event, (StatusCode, String)> {
+    let NewQuantity {
         ingredient_id,
-        user_id
+        quantity: ingredient_quantity,
+        unit_id,
+    } = new_quantity;
+
+    let ingredient = sqlx::query!(
+        r#"
+            SELECT i.unit_id, i.density FROM ingredient AS i
+            WHERE i.id = $1 AND EXISTS (
+                SELECT 1 FROM shopping_users($2) AS u WHERE has_access(i.user_id, i.household_id, u)
+            )
+        "#,
+        ingredient_id,
+        shopping_id
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await
     .map_err(|_| {
         (
@@ -65,72 +239,184 @@ pub async fn add_shopping_quantity(
         )
     })?;
 
+    let units = get_shopping_units(shopping_id, default_err.clone(), &mut *conn).await?;
+    let unit_id = unit_id.unwrap_or(ingredient.unit_id);
+    check_unit(
+        unit_id,
+        ingredient_id,
+        ingredient.unit_id,
+        ingredient.density,
+        &units,
+    )?;
+    let ingredient_quantity = check_bounds(Some(ingredient_quantity))?;
+
     let shopping_ingredient = sqlx::query_as!(
         ShoppingIngredientId,
         r#"SELECT id FROM shopping_ingredient WHERE shopping_id = $1 AND ingredient_id = $2"#,
         shopping_id,
         ingredient_id,
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|_| default_err.clone())?;
 
-    let shopping_ingredient = match shopping_ingredient {
-        Some(shopping_ingredient) => shopping_ingredient,
-        None => sqlx::query_as!(
-            ShoppingIngredientId,
-            r#"
+    let (shopping_ingredient, event) = match shopping_ingredient {
+        Some(shopping_ingredient) => {
+            let event = Event::QuantityChanged {
+                id: shopping_ingredient.id,
+            };
+            (shopping_ingredient, event)
+        }
+        None => {
+            let shopping_ingredient = sqlx::query_as!(
+                ShoppingIngredientId,
+                r#"
                 INSERT INTO shopping_ingredient ( shopping_id, ingredient_id, checked )
                 VALUES ( $1, $2, false) RETURNING id
             "#,
-            shopping_id,
-            ingredient_id,
-        )
-        .fetch_one(pool)
-        .await
-        .map_err(|_| default_err.clone())?,
+                shopping_id,
+                ingredient_id,
+            )
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| default_err.clone())?;
+            let event = Event::IngredientAdded {
+                id: shopping_ingredient.id,
+            };
+            (shopping_ingredient, event)
+        }
     };
 
     let shopping_quantity = sqlx::query!(
         r#"
-            SELECT quantity from shopping_quantity
+            SELECT quantity, unit_id from shopping_quantity
             WHERE shopping_ingredient_id = $1 AND recipe_id IS NOT DISTINCT FROM $2
         "#,
         shopping_ingredient.id,
         recipe_id
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|_| default_err.clone())?;
 
     if let Some(shopping_quantity) = shopping_quantity {
+        // Add to the existing quantity in its unit
+        let quantity = check_bounds(
+            convert(
+                ingredient_quantity,
+                find_unit(unit_id, &units)?,
+                find_unit(shopping_quantity.unit_id, &units)?,
+                ingredient.density,
+            )
+            .and_then(|quantity| shopping_quantity.quantity.checked_add(quantity)),
+        )?;
+
         sqlx::query!(
             r#"
                 UPDATE shopping_quantity
                 SET quantity = $1
                 WHERE shopping_ingredient_id = $2 AND recipe_id IS NOT DISTINCT FROM $3
             "#,
-            shopping_quantity.quantity + ingredient_quantity,
+            quantity,
             shopping_ingredient.id,
             recipe_id
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(|_| default_err.clone())?;
     } else {
         sqlx::query!(
             r#"
-                INSERT INTO shopping_quantity ( shopping_ingredient_id, recipe_id, quantity )
-                VALUES ( $1, $2, $3)
+                INSERT INTO shopping_quantity ( shopping_ingredient_id, recipe_id, quantity, unit_id )
+                VALUES ( $1, $2, $3, $4 )
             "#,
             shopping_ingredient.id,
             recipe_id,
             ingredient_quantity,
+            unit_id,
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(|_| default_err.clone())?;
     }
 
-    Ok(())
+    Ok(event)
+}
+
+/// Adds the ingredients of a recipe, attributed to the recipe so it can be removed again.
+/// Quantities are for the recipe's servings and get scaled to `servings` if given,
+/// all ingredients of the recipe are added if `ingredients` is omitted.
+pub async fn add_shopping_recipe(
+    recipe_id: i32,
+    servings: Option<i32>,
+    ingredients: Option<Vec<NewQuantity>>,
+    shopping_id: i32,
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<Vec<Event>, (StatusCode, String)> {
+    let recipe = sqlx::query!(r#"SELECT servings FROM recipe WHERE id = $1"#, recipe_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+    let ingredients = match ingredients {
+        Some(ingredients) => ingredients,
+        None => sqlx::query_as!(
+            NewQuantity,
+            r#"
+                SELECT ingredient_id, quantity, unit_id AS "unit_id?"
+                FROM recipe_quantity
+                WHERE recipe_id = $1
+            "#,
+            recipe_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?,
+    };
+
+    let ids: Vec<i32> = ingredients.iter().map(|i| i.ingredient_id).collect();
+
+    let default_units = sqlx::query!(
+        r#"SELECT id, unit_id FROM ingredient WHERE id = ANY($1)"#,
+        &ids
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let mut events = Vec::new();
+    for ingredient in ingredients.into_iter() {
+        let unit_id = ingredient.unit_id.or_else(|| {
+            default_units
+                .iter()
+                .find(|u| u.id == ingredient.ingredient_id)
+                .map(|u| u.unit_id)
+        });
+
+        let quantity = match servings {
+            Some(servings) => check_bounds(scale_quantity(
+                ingredient.quantity,
+                recipe.servings,
+                servings,
+            ))?,
+            None => ingredient.quantity,
+        };
+
+        let event = add_shopping_quantity(
+            NewQuantity {
+                ingredient_id: ingredient.ingredient_id,
+                quantity,
+                unit_id,
+            },
+            shopping_id,
+            Some(recipe_id),
+            default_err.clone(),
+            &mut *conn,
+        )
+        .await?;
+        events.push(event);
+    }
+
+    Ok(events)
 }
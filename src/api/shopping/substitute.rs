@@ -0,0 +1,154 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    shopping::{
+        events::{Event, ShoppingEvents},
+        service::{
+            add_shopping_quantity, get_shopping_units, validate_shopping_id, NewQuantity, Role,
+        },
+    },
+    substitutions::service::{get_substitution, substitute_quantity},
+};
+use axum::{
+    extract::{self, Path},
+    http::StatusCode,
+    Extension,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct SubstituteReq {
+    substitute_id: i32,
+}
+
+/// Replaces a shopping ingredient by a substitute. Every quantity is recomputed with the ratio of
+/// the substitution rule and stays attributed to its recipe. A substitute which isn't on the list
+/// yet takes over the check state and store of the replaced ingredient.
+#[axum_macros::debug_handler]
+pub async fn substitute(
+    claims: Claims,
+    Path((id, ingredient_id)): Path<(i32, i32)>,
+    extract::Json(payload): extract::Json<SubstituteReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed substituting shopping ingredient");
+
+    validate_shopping_id(
+        id,
+        claims.get_sub(),
+        Role::Editor,
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let shopping_ingredient = sqlx::query!(
+        r#"
+            SELECT id, ingredient_id, checked, checked_by, checked_at, store_id
+            FROM shopping_ingredient
+            WHERE id = $1 AND shopping_id = $2
+        "#,
+        ingredient_id,
+        id,
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Shopping ingredient not found".to_string(),
+    ))?;
+
+    let substitution = get_substitution(
+        shopping_ingredient.ingredient_id,
+        payload.substitute_id,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let units = get_shopping_units(id, default_err.clone(), &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let on_list = sqlx::query!(
+        r#"SELECT id FROM shopping_ingredient WHERE shopping_id = $1 AND ingredient_id = $2"#,
+        id,
+        payload.substitute_id
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .is_some();
+
+    let quantities = sqlx::query!(
+        r#"
+            SELECT quantity, unit_id, recipe_id FROM shopping_quantity
+            WHERE shopping_ingredient_id = $1
+            ORDER BY id
+        "#,
+        shopping_ingredient.id
+    )
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // On delete cascade for shopping_quantities
+    sqlx::query!(
+        r#"DELETE FROM shopping_ingredient WHERE id = $1"#,
+        shopping_ingredient.id
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let mut events = vec![Event::IngredientRemoved {
+        id: shopping_ingredient.id,
+    }];
+    for quantity in quantities.into_iter() {
+        let substituted =
+            substitute_quantity(quantity.quantity, quantity.unit_id, &substitution, &units)?;
+
+        let event = add_shopping_quantity(
+            NewQuantity {
+                ingredient_id: payload.substitute_id,
+                quantity: substituted,
+                unit_id: Some(substitution.substitute_unit_id),
+            },
+            id,
+            quantity.recipe_id,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+        events.push(event);
+    }
+
+    if !on_list {
+        sqlx::query!(
+            r#"
+                UPDATE shopping_ingredient
+                SET checked = $3, checked_by = $4, checked_at = $5, store_id = $6
+                WHERE shopping_id = $1 AND ingredient_id = $2
+            "#,
+            id,
+            payload.substitute_id,
+            shopping_ingredient.checked,
+            shopping_ingredient.checked_by,
+            shopping_ingredient.checked_at,
+            shopping_ingredient.store_id
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(id, events);
+
+    Ok(StatusCode::OK)
+}
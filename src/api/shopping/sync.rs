@@ -0,0 +1,449 @@
+use crate::api::{
+    auth::Claims,
+    global::get_default_err,
+    shopping::{
+        api::Quantities,
+        events::{Event, ShoppingEvents},
+        service::{add_shopping_quantity, set_checked, validate_shopping_id, NewQuantity, Role},
+    },
+    units::quantity::{self, validate_quantity},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// How long clients can stay offline, older operation timestamps are clamped to it. Applied
+/// operations and removals are only kept this long.
+pub const MAX_OFFLINE: Duration = Duration::days(30);
+
+#[derive(Deserialize)]
+pub struct SyncReq {
+    /// Sync token of the last sync, the whole list is returned without one and the client's
+    /// operations never conflict
+    sync_token: Option<i64>,
+    /// Changes made on the client while offline, applied in order
+    #[serde(default)]
+    operations: Vec<Operation>,
+}
+
+#[derive(Deserialize)]
+pub struct Operation {
+    /// Generated by the client, operations are only applied once
+    id: Uuid,
+    /// When the change was made on the client
+    #[serde(with = "time::serde::rfc3339")]
+    timestamp: OffsetDateTime,
+    #[serde(flatten)]
+    change: Change,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    Check {
+        shopping_ingredient_id: i32,
+    },
+    Uncheck {
+        shopping_ingredient_id: i32,
+    },
+    Add {
+        ingredient_id: i32,
+        #[serde(with = "quantity")]
+        quantity: Decimal,
+        unit_id: Option<i32>,
+    },
+    UpdateQuantity {
+        quantity_id: i32,
+        #[serde(with = "quantity")]
+        quantity: Decimal,
+    },
+}
+
+#[derive(Serialize)]
+pub struct SyncRes {
+    /// Has to be sent with the next sync to only get newer changes
+    sync_token: i64,
+    /// Operations applied by this or an earlier sync
+    applied: Vec<Uuid>,
+    /// Operations which weren't applied, the returned state wins
+    rejected: Vec<Rejected>,
+    /// Shopping ingredients added or changed since the sync token
+    ingredients: Vec<SyncIngredient>,
+    /// Shopping ingredients removed since the sync token
+    removed: Vec<i32>,
+    /// Without sync token, or with one older than the kept removals, `ingredients` is the whole
+    /// list and shopping ingredients missing from it were removed
+    full: bool,
+}
+
+#[derive(Serialize)]
+pub struct Rejected {
+    id: Uuid,
+    reason: String,
+}
+
+#[derive(Serialize)]
+pub struct SyncIngredient {
+    id: i32,
+    ingredient_id: i32,
+    name: String,
+    unit: String,
+    checked: bool,
+    /// Username of who checked the ingredient
+    checked_by: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    checked_at: Option<OffsetDateTime>,
+    store_id: Option<i32>,
+    quantities: Vec<Quantities>,
+}
+
+/// Applies operations of an offline client and returns everything changed since its last sync.
+///
+/// Checking and updating quantities are rejected if the shopping ingredient changed on the server
+/// since the client's last sync, changes made by earlier operations of the same sync don't count.
+/// Added quantities are summed up and never conflict.
+#[axum_macros::debug_handler]
+pub async fn sync(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Json(payload): Json<SyncReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<(StatusCode, Json<SyncRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed syncing shopping list");
+
+    let role = match payload.operations.is_empty() {
+        true => Role::Viewer,
+        false => Role::Editor,
+    };
+    validate_shopping_id(id, claims.get_sub(), role, default_err.clone(), &pool).await?;
+
+    let mut applied = Vec::new();
+    let mut rejected = Vec::new();
+    let mut events = Vec::new();
+    // Versions of the shopping ingredients changed by the operations applied so far
+    let mut own_versions = HashMap::new();
+
+    for operation in payload.operations.into_iter() {
+        // Every operation is applied on its own, so a rejected one doesn't undo the others
+        let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+        let new = sqlx::query!(
+            r#"
+                INSERT INTO shopping_sync_operation ( id, shopping_id ) VALUES ( $1, $2 )
+                ON CONFLICT DO NOTHING
+                RETURNING id
+            "#,
+            operation.id,
+            id
+        )
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        if new.is_none() {
+            applied.push(operation.id);
+            continue;
+        }
+
+        let result = apply_operation(
+            &operation,
+            id,
+            claims.get_sub(),
+            payload.sync_token,
+            &own_versions,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await;
+
+        match result {
+            Ok(event) => {
+                let version = sqlx::query!(
+                    r#"SELECT version FROM shopping_ingredient WHERE id = $1"#,
+                    event.id()
+                )
+                .fetch_one(&mut tx)
+                .await
+                .map_err(|_| default_err.clone())?;
+
+                tx.commit().await.map_err(|_| default_err.clone())?;
+                applied.push(operation.id);
+                own_versions.insert(event.id(), version.version);
+                events.push(event);
+            }
+            // Failing on the server isn't the operation's fault, the client has to retry
+            Err((status, _)) if status.is_server_error() => {
+                shopping_events.publish(id, events);
+                return Err(default_err);
+            }
+            Err((_, reason)) => rejected.push(Rejected {
+                id: operation.id,
+                reason,
+            }),
+        }
+    }
+
+    shopping_events.publish(id, events);
+
+    // Taken before the delta, changes made in between are returned again by the next sync.
+    // Versions are handed out under the list's row lock, so every change up to it is committed
+    let latest = sqlx::query!(
+        r#"SELECT version, pruned_version FROM shopping WHERE id = $1"#,
+        id
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // Removals after an older sync token may be pruned already
+    let sync_token = payload
+        .sync_token
+        .filter(|sync_token| *sync_token >= latest.pruned_version);
+    let since = sync_token.unwrap_or(0);
+
+    let ingredients = sqlx::query!(
+        r#"
+            SELECT si.id, si.ingredient_id, i.name, u.name AS unit, si.checked,
+            cu.username AS "checked_by?", si.checked_at, si.store_id
+            FROM shopping_ingredient AS si
+            JOIN ingredient AS i ON si.ingredient_id = i.id
+            JOIN unit AS u ON i.unit_id = u.id
+            LEFT JOIN "user" AS cu ON si.checked_by = cu.id
+            WHERE si.shopping_id = $1 AND si.version > $2
+            ORDER BY si.id
+        "#,
+        id,
+        since
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let ids: Vec<i32> = ingredients.iter().map(|i| i.id).collect();
+
+    let quantities = sqlx::query_as!(
+        Quantities,
+        r#"
+            SELECT sq.id, sq.shopping_ingredient_id, sq.quantity, u.id AS unit_id, u.name AS unit,
+            r.id AS "recipe_id?", r.name AS "recipe_name?"
+            FROM shopping_quantity AS sq
+            JOIN unit AS u ON sq.unit_id = u.id
+            LEFT JOIN recipe AS r ON sq.recipe_id = r.id
+            WHERE shopping_ingredient_id = ANY($1)
+            ORDER BY sq.id
+        "#,
+        &ids
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    // A client without sync token has nothing to remove
+    let removed = match sync_token {
+        Some(sync_token) => sqlx::query!(
+            r#"
+                SELECT shopping_ingredient_id FROM shopping_ingredient_removed
+                WHERE shopping_id = $1 AND version > $2
+                ORDER BY version
+            "#,
+            id,
+            sync_token
+        )
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| default_err.clone())?
+        .into_iter()
+        .map(|record| record.shopping_ingredient_id)
+        .collect(),
+        None => Vec::new(),
+    };
+
+    let ingredients = ingredients
+        .into_iter()
+        .map(|i| SyncIngredient {
+            id: i.id,
+            ingredient_id: i.ingredient_id,
+            name: i.name,
+            unit: i.unit,
+            checked: i.checked,
+            checked_by: i.checked_by,
+            checked_at: i.checked_at,
+            store_id: i.store_id,
+            quantities: quantities
+                .iter()
+                .filter(|q| q.shopping_ingredient_id == i.id)
+                .cloned()
+                .collect(),
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(SyncRes {
+            sync_token: latest.version,
+            applied,
+            rejected,
+            ingredients,
+            removed,
+            full: sync_token.is_none(),
+        }),
+    ))
+}
+
+async fn apply_operation(
+    operation: &Operation,
+    shopping_id: i32,
+    user_id: Uuid,
+    sync_token: Option<i64>,
+    own_versions: &HashMap<i32, i64>,
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<Event, (StatusCode, String)> {
+    match operation.change {
+        Change::Check {
+            shopping_ingredient_id: id,
+        }
+        | Change::Uncheck {
+            shopping_ingredient_id: id,
+        } => {
+            // Locked so nothing changes it between the conflict check and the update
+            let shopping_ingredient = sqlx::query!(
+                r#"
+                    SELECT version FROM shopping_ingredient
+                    WHERE id = $1 AND shopping_id = $2
+                    FOR UPDATE
+                "#,
+                id,
+                shopping_id
+            )
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| default_err.clone())?
+            .ok_or((
+                StatusCode::NOT_FOUND,
+                "Shopping ingredient not found".to_string(),
+            ))?;
+
+            check_conflict(id, shopping_ingredient.version, sync_token, own_versions)?;
+
+            // Recorded as checked when it happened on the client, as far as its clock can be
+            // trusted
+            let now = OffsetDateTime::now_utc();
+            let checked_at = operation.timestamp.clamp(now - MAX_OFFLINE, now);
+
+            let checked = matches!(operation.change, Change::Check { .. });
+            set_checked(
+                shopping_id,
+                Some(&[id]),
+                checked,
+                user_id,
+                checked_at,
+                default_err,
+                &mut *conn,
+            )
+            .await?;
+
+            Ok(Event::IngredientChecked { id, checked })
+        }
+        Change::Add {
+            ingredient_id,
+            quantity,
+            unit_id,
+        } => {
+            validate_sync_quantity(quantity)?;
+
+            add_shopping_quantity(
+                NewQuantity {
+                    ingredient_id,
+                    quantity,
+                    unit_id,
+                },
+                shopping_id,
+                None,
+                default_err,
+                conn,
+            )
+            .await
+        }
+        Change::UpdateQuantity {
+            quantity_id: id,
+            quantity,
+        } => {
+            validate_sync_quantity(quantity)?;
+
+            // Locked so nothing changes it between the conflict check and the update
+            let shopping_ingredient = sqlx::query!(
+                r#"
+                    SELECT si.id, si.version
+                    FROM shopping_quantity AS sq
+                    JOIN shopping_ingredient AS si ON sq.shopping_ingredient_id = si.id
+                    WHERE sq.id = $1 AND si.shopping_id = $2
+                    FOR UPDATE OF si
+                "#,
+                id,
+                shopping_id
+            )
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| default_err.clone())?
+            .ok_or((
+                StatusCode::NOT_FOUND,
+                "Shopping quantity not found".to_string(),
+            ))?;
+
+            check_conflict(
+                shopping_ingredient.id,
+                shopping_ingredient.version,
+                sync_token,
+                own_versions,
+            )?;
+
+            sqlx::query!(
+                r#"UPDATE shopping_quantity SET quantity = $1 WHERE id = $2"#,
+                quantity,
+                id
+            )
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| default_err)?;
+
+            Ok(Event::QuantityChanged {
+                id: shopping_ingredient.id,
+            })
+        }
+    }
+}
+
+fn check_conflict(
+    shopping_ingredient_id: i32,
+    version: i64,
+    sync_token: Option<i64>,
+    own_versions: &HashMap<i32, i64>,
+) -> Result<(), (StatusCode, String)> {
+    match sync_token {
+        Some(sync_token)
+            if version > sync_token
+                && own_versions.get(&shopping_ingredient_id) != Some(&version) =>
+        {
+            Err((
+                StatusCode::CONFLICT,
+                "Shopping ingredient was changed in the meantime".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_sync_quantity(quantity: Decimal) -> Result<(), (StatusCode, String)> {
+    validate_quantity(&quantity).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Quantity has to be greater than 0 and less than 100000000".to_string(),
+        )
+    })
+}
@@ -0,0 +1,9 @@
+pub mod api;
+pub mod recurrence;
+pub use api::apply;
+pub use api::create;
+pub use api::delete;
+pub use api::get;
+pub use api::get_all;
+pub use api::update;
+mod service;
@@ -0,0 +1,402 @@
+use crate::api::{
+    auth::Claims,
+    date,
+    global::{get_default_err, nullable, ValidatedJson},
+    households::service::{validate_household_id, HouseholdRole},
+    shopping::{
+        events::ShoppingEvents,
+        service::{validate_shopping_id, Role},
+    },
+    shopping_templates::service::{
+        add_template_quantities, save_template_ingredients, today, validate_template_id, Recurrence,
+    },
+    units::quantity::{self, validate_quantity},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::Date;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    pub name: String,
+    pub household_id: Option<i32>,
+    pub recurrence: Option<String>,
+    #[serde(with = "date::option")]
+    pub next_run: Option<Date>,
+    pub ingredients: i64,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let templates = sqlx::query_as!(
+        GetAllRes,
+        r#"
+            SELECT t.id, t.name, t.household_id, t.recurrence, t.next_run,
+            count(tq.id) AS "ingredients!"
+            FROM shopping_template AS t
+            LEFT OUTER JOIN shopping_template_quantity AS tq ON t.id = tq.shopping_template_id
+            WHERE has_access(t.user_id, t.household_id, $1)
+            GROUP BY t.id
+            ORDER BY t.name
+        "#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting templates"))?;
+
+    Ok((StatusCode::OK, Json(templates)))
+}
+
+#[derive(Serialize)]
+pub struct GetRes {
+    pub id: i32,
+    pub name: String,
+    pub household_id: Option<i32>,
+    pub recurrence: Option<String>,
+    #[serde(with = "date::option")]
+    pub next_run: Option<Date>,
+    pub ingredients: Vec<Ingredient>,
+}
+
+#[derive(Serialize)]
+pub struct Ingredient {
+    pub id: i32,
+    pub name: String,
+    #[serde(with = "quantity")]
+    pub quantity: Decimal,
+    pub unit_id: i32,
+    pub unit: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<GetRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting template");
+
+    let template = sqlx::query!(
+        r#"
+            SELECT id, name, household_id, recurrence, next_run FROM shopping_template
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Template not found".to_string()))?;
+
+    let ingredients = sqlx::query_as!(
+        Ingredient,
+        r#"
+            SELECT i.id, i.name, tq.quantity, u.id AS unit_id, u.name AS unit
+            FROM shopping_template_quantity AS tq
+            JOIN ingredient AS i ON tq.ingredient_id = i.id
+            JOIN unit AS u ON tq.unit_id = u.id
+            WHERE tq.shopping_template_id = $1
+            ORDER BY tq.id
+        "#,
+        id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetRes {
+            id: template.id,
+            name: template.name,
+            household_id: template.household_id,
+            recurrence: template.recurrence,
+            next_run: template.next_run,
+            ingredients,
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct IngredientWithQuantity {
+    pub id: i32,
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Quantity has to be greater than 0 and less than 100000000"
+    ))]
+    pub quantity: Decimal,
+    /// Defaults to the unit of the ingredient
+    pub unit_id: Option<i32>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: String,
+    /// Creates the template in a household instead of the personal library
+    household_id: Option<i32>,
+    #[validate]
+    ingredients: Vec<IngredientWithQuantity>,
+    /// Creates a shopping list from the template on this schedule
+    recurrence: Option<Recurrence>,
+    /// Date of the first recurring list, defaults to today
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    next_run: Option<Date>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating template");
+
+    if let Some(household_id) = payload.household_id {
+        validate_household_id(
+            household_id,
+            claims.get_sub(),
+            HouseholdRole::Member,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    let next_run = payload
+        .recurrence
+        .map(|_| payload.next_run.unwrap_or_else(today));
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let template = sqlx::query!(
+        r#"
+            INSERT INTO shopping_template ( name, user_id, household_id, recurrence, next_run, anchor )
+            VALUES ( $1, $2, $3, $4, $5, $5 )
+            RETURNING id
+        "#,
+        payload.name,
+        claims.get_sub(),
+        payload.household_id,
+        payload.recurrence.map(|r| r.as_str()),
+        next_run
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    save_template_ingredients(
+        template.id,
+        payload.household_id,
+        claims.get_sub(),
+        &payload.ingredients,
+        default_err.clone(),
+        &mut tx,
+    )
+    .await?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: Option<String>,
+    /// Replaces all ingredients of the template
+    #[validate]
+    ingredients: Option<Vec<IngredientWithQuantity>>,
+    /// `null` stops creating lists on a schedule
+    #[serde(default, deserialize_with = "nullable")]
+    recurrence: Option<Option<Recurrence>>,
+    #[serde(default, deserialize_with = "date::option::deserialize")]
+    next_run: Option<Date>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating template");
+
+    let template = validate_template_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    if let Some(name) = payload.name {
+        sqlx::query!(
+            r#"UPDATE shopping_template SET name = $1 WHERE id = $2"#,
+            name,
+            id
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some(ingredients) = payload.ingredients {
+        sqlx::query!(
+            r#"DELETE FROM shopping_template_quantity WHERE shopping_template_id = $1"#,
+            id
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        save_template_ingredients(
+            id,
+            template.household_id,
+            claims.get_sub(),
+            &ingredients,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+    }
+
+    match payload.recurrence {
+        Some(recurrence) => {
+            let next_run = recurrence.map(|_| payload.next_run.unwrap_or_else(today));
+            sqlx::query!(
+                r#"
+                    UPDATE shopping_template SET recurrence = $1, next_run = $2, anchor = $2
+                    WHERE id = $3
+                "#,
+                recurrence.map(|r| r.as_str()),
+                next_run,
+                id
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|_| default_err.clone())?;
+        }
+        None => {
+            if let Some(next_run) = payload.next_run {
+                sqlx::query!(
+                    r#"
+                        UPDATE shopping_template SET next_run = $1, anchor = $1
+                        WHERE id = $2 AND recurrence IS NOT NULL
+                    "#,
+                    next_run,
+                    id
+                )
+                .execute(&mut tx)
+                .await
+                .map_err(|_| default_err.clone())?;
+            }
+        }
+    }
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed deleting template");
+
+    validate_template_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    sqlx::query!(r#"DELETE FROM shopping_template WHERE id = $1"#, id)
+        .execute(&pool)
+        .await
+        .map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ApplyReq {
+    /// List to add the template to, a new list is created if omitted
+    shopping_id: Option<i32>,
+    /// Name of the new list, defaults to the name of the template
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ApplyRes {
+    id: i32,
+}
+
+/// Adds the ingredients of a template to a list. New lists are created in the template's
+/// household.
+#[axum_macros::debug_handler]
+pub async fn apply(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<ApplyReq>,
+    Extension(pool): Extension<PgPool>,
+    Extension(shopping_events): Extension<ShoppingEvents>,
+) -> Result<(StatusCode, Json<ApplyRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed applying template");
+
+    let template = validate_template_id(id, claims.get_sub(), default_err.clone(), &pool).await?;
+
+    if let Some(shopping_id) = payload.shopping_id {
+        if payload.name.is_some() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Name can only be set for a new shopping list".to_string(),
+            ));
+        }
+
+        validate_shopping_id(
+            shopping_id,
+            claims.get_sub(),
+            Role::Editor,
+            default_err.clone(),
+            &pool,
+        )
+        .await?;
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let shopping_id = match payload.shopping_id {
+        Some(shopping_id) => shopping_id,
+        None => {
+            sqlx::query!(
+                r#"
+                    INSERT INTO shopping ( name, user_id, household_id )
+                    VALUES ( $1, $2, $3 )
+                    RETURNING id
+                "#,
+                payload.name.unwrap_or(template.name),
+                claims.get_sub(),
+                template.household_id
+            )
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|_| default_err.clone())?
+            .id
+        }
+    };
+
+    let events = add_template_quantities(id, shopping_id, default_err.clone(), &mut tx).await?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    shopping_events.publish(shopping_id, events);
+
+    Ok((StatusCode::CREATED, Json(ApplyRes { id: shopping_id })))
+}
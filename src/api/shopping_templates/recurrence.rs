@@ -0,0 +1,111 @@
+use crate::api::{
+    global::get_default_err,
+    shopping_templates::service::{add_template_quantities, today},
+};
+use axum::http::StatusCode;
+use sqlx::{Connection, PgPool};
+use std::time::Duration;
+
+/// How often due templates are looked for
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Creates the lists of recurring templates in the background, forever
+pub async fn run(pool: PgPool) {
+    let mut interval = tokio::time::interval(INTERVAL);
+    loop {
+        interval.tick().await;
+        match create_due_lists(&pool).await {
+            Ok(skipped) if !skipped.is_empty() => {
+                println!("Skipped recurring templates with ids {:?}", skipped)
+            }
+            Ok(_) => (),
+            Err((_, message)) => println!("{}", message),
+        }
+    }
+}
+
+/// Creates a list for every template whose next run is due and returns the templates which were
+/// skipped. The list belongs to the creator of the template, missed runs only create a single list.
+async fn create_due_lists(pool: &PgPool) -> Result<Vec<i32>, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating recurring shopping lists");
+    let mut skipped = Vec::new();
+
+    loop {
+        let today = today();
+        let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+        // Locked so several servers don't create the same list
+        let template = sqlx::query!(
+            r#"
+                SELECT id, name, user_id, household_id FROM shopping_template
+                WHERE next_run <= $1
+                ORDER BY next_run, id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            "#,
+            today
+        )
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        let template = match template {
+            Some(template) => template,
+            None => return Ok(skipped),
+        };
+
+        // The list is created in a savepoint, so a failed run keeps the template locked
+        let mut savepoint = Connection::begin(&mut *tx)
+            .await
+            .map_err(|_| default_err.clone())?;
+
+        let shopping = sqlx::query!(
+            r#"
+                INSERT INTO shopping ( name, user_id, household_id )
+                VALUES ( $1, $2, $3 )
+                RETURNING id
+            "#,
+            template.name,
+            template.user_id,
+            template.household_id
+        )
+        .fetch_one(&mut savepoint)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        let added = add_template_quantities(
+            template.id,
+            shopping.id,
+            default_err.clone(),
+            &mut savepoint,
+        )
+        .await;
+        match added {
+            Ok(_) => savepoint.commit().await.map_err(|_| default_err.clone())?,
+            // A template whose ingredients can't be added anymore is skipped until its next run
+            Err((status, _)) if status.is_client_error() => {
+                skipped.push(template.id);
+                savepoint
+                    .rollback()
+                    .await
+                    .map_err(|_| default_err.clone())?;
+            }
+            // Anything else is retried by the next check
+            Err(err) => return Err(err),
+        }
+
+        sqlx::query!(
+            r#"
+                UPDATE shopping_template SET next_run = next_run_after(recurrence, anchor, $2)
+                WHERE id = $1
+            "#,
+            template.id,
+            today
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|_| default_err.clone())?;
+
+        tx.commit().await.map_err(|_| default_err.clone())?;
+    }
+}
@@ -0,0 +1,145 @@
+use super::api::IngredientWithQuantity;
+use crate::api::{
+    shopping::{
+        events::Event,
+        service::{add_shopping_quantity, NewQuantity},
+    },
+    units::service::{check_unit, get_units},
+};
+use axum::http::StatusCode;
+use serde::Deserialize;
+use sqlx::{PgConnection, PgPool};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+/// Recurring lists are scheduled in UTC, by the API and by the background job alike
+pub fn today() -> Date {
+    OffsetDateTime::now_utc().date()
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Recurrence::Daily => "daily",
+            Recurrence::Weekly => "weekly",
+            Recurrence::Monthly => "monthly",
+        }
+    }
+}
+
+pub struct TemplateOwner {
+    pub name: String,
+    pub household_id: Option<i32>,
+}
+
+/// Name and household of a template the user has access to
+pub async fn validate_template_id(
+    id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<TemplateOwner, (StatusCode, String)> {
+    sqlx::query_as!(
+        TemplateOwner,
+        r#"
+            SELECT name, household_id FROM shopping_template
+            WHERE id = $1 AND has_access(user_id, household_id, $2)
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Template not found".to_string()))
+}
+
+/// Ingredients have to be accessible to the user, household templates can only use ingredients of
+/// their household
+pub async fn save_template_ingredients(
+    template_id: i32,
+    household_id: Option<i32>,
+    user_id: Uuid,
+    ingredients: &[IngredientWithQuantity],
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    let units = get_units(user_id, default_err.clone(), &mut *conn).await?;
+
+    for ingredient in ingredients.iter() {
+        let record = sqlx::query!(
+            r#"
+                SELECT id, unit_id, density FROM ingredient
+                WHERE id = $1 AND has_access(user_id, household_id, $2)
+                AND ($3::integer IS NULL OR household_id = $3)
+            "#,
+            ingredient.id,
+            user_id,
+            household_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Ingredient with id {} not found", ingredient.id),
+        ))?;
+
+        let unit_id = ingredient.unit_id.unwrap_or(record.unit_id);
+        check_unit(unit_id, record.id, record.unit_id, record.density, &units)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO shopping_template_quantity ( shopping_template_id, ingredient_id, quantity, unit_id )
+                VALUES ( $1, $2, $3, $4 )
+            "#,
+            template_id,
+            ingredient.id,
+            ingredient.quantity,
+            unit_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| default_err.clone())?;
+    }
+    Ok(())
+}
+
+/// Adds the quantities of a template to a shopping list
+pub async fn add_template_quantities(
+    template_id: i32,
+    shopping_id: i32,
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<Vec<Event>, (StatusCode, String)> {
+    let quantities = sqlx::query_as!(
+        NewQuantity,
+        r#"
+            SELECT ingredient_id, quantity, unit_id AS "unit_id?"
+            FROM shopping_template_quantity
+            WHERE shopping_template_id = $1
+            ORDER BY id
+        "#,
+        template_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let mut events = Vec::new();
+    for quantity in quantities.into_iter() {
+        let event =
+            add_shopping_quantity(quantity, shopping_id, None, default_err.clone(), &mut *conn)
+                .await?;
+        events.push(event);
+    }
+    Ok(events)
+}
@@ -0,0 +1,7 @@
+pub mod api;
+pub use api::create;
+pub use api::delete;
+pub use api::get;
+pub use api::get_all;
+pub use api::update;
+pub mod service;
@@ -0,0 +1,258 @@
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    stores::service::{save_store_categories, save_store_ingredients, validate_store_name},
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    pub name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let stores = sqlx::query_as!(
+        GetAllRes,
+        r#"SELECT id, name FROM store WHERE user_id = $1 ORDER BY name"#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting stores"))?;
+
+    Ok((StatusCode::OK, Json(stores)))
+}
+
+#[derive(Serialize)]
+pub struct GetRes {
+    pub id: i32,
+    pub name: String,
+    /// Walking order of the categories in the store
+    pub categories: Vec<Sorted>,
+    /// Walking order of the ingredients within their category
+    pub ingredients: Vec<Sorted>,
+}
+
+#[derive(Serialize)]
+pub struct Sorted {
+    pub id: i32,
+    pub name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<GetRes>), (StatusCode, String)> {
+    let default_err = get_default_err("Failed getting store");
+
+    let store = sqlx::query!(
+        r#"SELECT id, name FROM store WHERE id = $1 AND user_id = $2"#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Store not found".to_string()))?;
+
+    let categories = sqlx::query_as!(
+        Sorted,
+        r#"
+            SELECT c.id, c.name FROM store_category AS sc
+            JOIN category AS c ON sc.category_id = c.id
+            WHERE sc.store_id = $1
+            ORDER BY sc.sort
+        "#,
+        id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let ingredients = sqlx::query_as!(
+        Sorted,
+        r#"
+            SELECT i.id, i.name FROM store_ingredient AS si
+            JOIN ingredient AS i ON si.ingredient_id = i.id
+            WHERE si.store_id = $1
+            ORDER BY si.sort
+        "#,
+        id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetRes {
+            id: store.id,
+            name: store.name,
+            categories,
+            ingredients,
+        }),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: String,
+    /// Category ids in walking order, unlisted categories come after them
+    #[serde(default)]
+    pub categories: Vec<i32>,
+    /// Ingredient ids in walking order, unlisted ingredients come after them
+    #[serde(default)]
+    pub ingredients: Vec<i32>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating store");
+
+    validate_store_name(
+        &payload.name,
+        None,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    let store = sqlx::query!(
+        r#"INSERT INTO store ( name, user_id ) VALUES ( $1, $2 ) RETURNING id"#,
+        payload.name,
+        claims.get_sub()
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    save_store_categories(
+        store.id,
+        claims.get_sub(),
+        &payload.categories,
+        default_err.clone(),
+        &mut tx,
+    )
+    .await?;
+
+    save_store_ingredients(
+        store.id,
+        claims.get_sub(),
+        &payload.ingredients,
+        default_err.clone(),
+        &mut tx,
+    )
+    .await?;
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: Option<String>,
+    /// Replaces the order of the categories
+    pub categories: Option<Vec<i32>>,
+    /// Replaces the order of the ingredients
+    pub ingredients: Option<Vec<i32>>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating store");
+
+    if let Some(ref name) = payload.name {
+        validate_store_name(name, Some(id), claims.get_sub(), default_err.clone(), &pool).await?;
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| default_err.clone())?;
+
+    sqlx::query!(
+        r#"
+            UPDATE store SET name = COALESCE($1, name)
+            WHERE id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        payload.name,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Store not found".to_string()))?;
+
+    if let Some(categories) = payload.categories {
+        save_store_categories(
+            id,
+            claims.get_sub(),
+            &categories,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+    }
+
+    if let Some(ingredients) = payload.ingredients {
+        save_store_ingredients(
+            id,
+            claims.get_sub(),
+            &ingredients,
+            default_err.clone(),
+            &mut tx,
+        )
+        .await?;
+    }
+
+    tx.commit().await.map_err(|_| default_err)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM store
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting store"))?
+    .ok_or((StatusCode::NOT_FOUND, "Store not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
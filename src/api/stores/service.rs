@@ -0,0 +1,142 @@
+use axum::http::StatusCode;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Store names have to be unique per user
+pub async fn validate_store_name(
+    name: &str,
+    store_id: Option<i32>,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let existing = sqlx::query!(
+        r#"
+            SELECT id FROM store
+            WHERE lower(name) = lower($1) AND user_id = $2 AND id IS DISTINCT FROM $3
+        "#,
+        name,
+        user_id,
+        store_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    if existing.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Store already exists".to_string()));
+    }
+    Ok(())
+}
+
+/// Stores of the user and of everyone sharing a household with them can be shopped at
+pub async fn validate_store_id(
+    store_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"SELECT id FROM store WHERE id = $1 AND user_id IN (SELECT co_members($2))"#,
+        store_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Store not found".to_string()))
+    .map(|_| ())
+}
+
+/// Replaces the order of categories in a store, categories are walked past in the given order
+pub async fn save_store_categories(
+    store_id: i32,
+    user_id: Uuid,
+    categories: &[i32],
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    check_unique(categories, "Categories")?;
+
+    sqlx::query!(
+        r#"DELETE FROM store_category WHERE store_id = $1"#,
+        store_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let inserted = sqlx::query!(
+        r#"
+            INSERT INTO store_category ( store_id, category_id, sort )
+            SELECT $1, c.id, o.sort::integer
+            FROM unnest($2::integer[]) WITH ORDINALITY AS o(id, sort)
+            JOIN category AS c ON o.id = c.id
+            WHERE has_access(c.user_id, c.household_id, $3)
+        "#,
+        store_id,
+        categories,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err)?;
+
+    if inserted.rows_affected() != categories.len() as u64 {
+        return Err((StatusCode::NOT_FOUND, "Category not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Replaces the order of ingredients in a store. Ingredients are only ordered within their
+/// category, so the order of the categories still applies.
+pub async fn save_store_ingredients(
+    store_id: i32,
+    user_id: Uuid,
+    ingredients: &[i32],
+    default_err: (StatusCode, String),
+    conn: &mut PgConnection,
+) -> Result<(), (StatusCode, String)> {
+    check_unique(ingredients, "Ingredients")?;
+
+    sqlx::query!(
+        r#"DELETE FROM store_ingredient WHERE store_id = $1"#,
+        store_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    let inserted = sqlx::query!(
+        r#"
+            INSERT INTO store_ingredient ( store_id, ingredient_id, sort )
+            SELECT $1, i.id, o.sort::integer
+            FROM unnest($2::integer[]) WITH ORDINALITY AS o(id, sort)
+            JOIN ingredient AS i ON o.id = i.id
+            WHERE has_access(i.user_id, i.household_id, $3)
+        "#,
+        store_id,
+        ingredients,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| default_err)?;
+
+    if inserted.rows_affected() != ingredients.len() as u64 {
+        return Err((StatusCode::NOT_FOUND, "Ingredient not found".to_string()));
+    }
+    Ok(())
+}
+
+fn check_unique(ids: &[i32], what: &str) -> Result<(), (StatusCode, String)> {
+    let unique: HashSet<&i32> = ids.iter().collect();
+    if unique.len() != ids.len() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{} can only appear once in a store", what),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    units::quantity::{self, validate_quantity},
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Deserialize)]
+pub struct GetAllQuery {
+    /// Only substitutes of this ingredient
+    pub ingredient_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    pub ingredient: Ingredient,
+    pub substitute: Ingredient,
+    /// Amount of the substitute's unit replacing one of the ingredient's unit
+    #[serde(with = "quantity")]
+    pub ratio: Decimal,
+    /// Whether the rule belongs to the user, rules of household members can only be used
+    pub owned: bool,
+}
+
+#[derive(Serialize)]
+pub struct Ingredient {
+    pub id: i32,
+    pub name: String,
+    pub unit: String,
+}
+
+/// Substitution rules of the user and of everyone sharing a household with them
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Query(query): Query<GetAllQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let substitutions = sqlx::query!(
+        r#"
+            SELECT s.id, s.ratio, s.user_id = $1 AS "owned!",
+            i.id AS ingredient_id, i.name AS ingredient_name, iu.name AS ingredient_unit,
+            si.id AS substitute_id, si.name AS substitute_name, su.name AS substitute_unit
+            FROM substitution AS s
+            JOIN ingredient AS i ON s.ingredient_id = i.id
+            JOIN unit AS iu ON i.unit_id = iu.id
+            JOIN ingredient AS si ON s.substitute_id = si.id
+            JOIN unit AS su ON si.unit_id = su.id
+            WHERE s.user_id IN (SELECT co_members($1))
+            AND ($2::integer IS NULL OR s.ingredient_id = $2)
+            ORDER BY i.name, si.name, s.id
+        "#,
+        claims.get_sub(),
+        query.ingredient_id
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting substitutions"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(
+            substitutions
+                .into_iter()
+                .map(|record| GetAllRes {
+                    id: record.id,
+                    ingredient: Ingredient {
+                        id: record.ingredient_id,
+                        name: record.ingredient_name,
+                        unit: record.ingredient_unit,
+                    },
+                    substitute: Ingredient {
+                        id: record.substitute_id,
+                        name: record.substitute_name,
+                        unit: record.substitute_unit,
+                    },
+                    ratio: record.ratio,
+                    owned: record.owned,
+                })
+                .collect(),
+        ),
+    ))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    ingredient_id: i32,
+    substitute_id: i32,
+    /// Amount of the substitute's unit replacing one of the ingredient's unit
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Ratio has to be greater than 0 and less than 100000000"
+    ))]
+    ratio: Decimal,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating substitution");
+
+    if payload.ingredient_id == payload.substitute_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "An ingredient can't substitute itself".to_string(),
+        ));
+    }
+
+    let ingredients = sqlx::query!(
+        r#"
+            SELECT count(*) AS "count!" FROM ingredient
+            WHERE id IN ($1, $2) AND has_access(user_id, household_id, $3)
+        "#,
+        payload.ingredient_id,
+        payload.substitute_id,
+        claims.get_sub()
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    if ingredients.count != 2 {
+        return Err((StatusCode::NOT_FOUND, "Ingredient not found".to_string()));
+    }
+
+    let existing = sqlx::query!(
+        r#"
+            SELECT id FROM substitution
+            WHERE user_id = $1 AND ingredient_id = $2 AND substitute_id = $3
+        "#,
+        claims.get_sub(),
+        payload.ingredient_id,
+        payload.substitute_id
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?;
+
+    if existing.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Substitution already exists".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+            INSERT INTO substitution ( ingredient_id, substitute_id, ratio, user_id )
+            VALUES ( $1, $2, $3, $4 )
+        "#,
+        payload.ingredient_id,
+        payload.substitute_id,
+        payload.ratio,
+        claims.get_sub()
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Ratio has to be greater than 0 and less than 100000000"
+    ))]
+    ratio: Decimal,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            UPDATE substitution SET ratio = $1
+            WHERE id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        payload.ratio,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed updating substitution"))?
+    .ok_or((StatusCode::NOT_FOUND, "Substitution not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM substitution
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting substitution"))?
+    .ok_or((StatusCode::NOT_FOUND, "Substitution not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
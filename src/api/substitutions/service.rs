@@ -0,0 +1,87 @@
+use crate::api::units::{
+    quantity::{check_bounds, SCALE},
+    service::{convert, Unit},
+};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Rule to replace an ingredient, `ratio` of the substitute's unit replace one of the
+/// ingredient's unit
+pub struct Substitution {
+    pub ingredient_unit_id: i32,
+    pub ingredient_density: Option<Decimal>,
+    pub substitute_unit_id: i32,
+    pub substitute_density: Option<Decimal>,
+    pub ratio: Decimal,
+}
+
+/// Substitution rule of the user or of anyone sharing a household with them, the user's own rule
+/// wins
+pub async fn get_substitution(
+    ingredient_id: i32,
+    substitute_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Substitution, (StatusCode, String)> {
+    sqlx::query_as!(
+        Substitution,
+        r#"
+            SELECT i.unit_id AS ingredient_unit_id, i.density AS ingredient_density,
+            si.unit_id AS substitute_unit_id, si.density AS substitute_density, s.ratio
+            FROM substitution AS s
+            JOIN ingredient AS i ON s.ingredient_id = i.id
+            JOIN ingredient AS si ON s.substitute_id = si.id
+            WHERE s.ingredient_id = $1 AND s.substitute_id = $2
+            AND s.user_id IN (SELECT co_members($3))
+            ORDER BY s.user_id = $3 DESC, s.id
+            LIMIT 1
+        "#,
+        ingredient_id,
+        substitute_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Substitution not found".to_string()))
+}
+
+/// Quantity of the substitute in its unit replacing a quantity of the ingredient
+pub fn substitute_quantity(
+    quantity: Decimal,
+    unit_id: i32,
+    substitution: &Substitution,
+    units: &[Unit],
+) -> Result<Decimal, (StatusCode, String)> {
+    let from = units.iter().find(|u| u.id == unit_id);
+    let to = units
+        .iter()
+        .find(|u| u.id == substitution.ingredient_unit_id);
+
+    let converted = from
+        .zip(to)
+        .and_then(|(from, to)| convert(quantity, from, to, substitution.ingredient_density))
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unit with id {} can't be converted to the unit of the substituted ingredient",
+                unit_id
+            ),
+        ))?;
+
+    let substituted = check_bounds(converted.checked_mul(substitution.ratio))?
+        .round_dp(SCALE)
+        .normalize();
+
+    // Tiny quantities with a small ratio can round away
+    if substituted.is_zero() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Quantity of the substitute would be 0".to_string(),
+        ));
+    }
+    Ok(substituted)
+}
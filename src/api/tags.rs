@@ -0,0 +1,6 @@
+pub mod api;
+pub use api::create;
+pub use api::delete;
+pub use api::get_all;
+pub use api::update;
+mod service;
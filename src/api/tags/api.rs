@@ -0,0 +1,140 @@
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    tags::service::validate_tag_name,
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use validator::Validate;
+
+#[derive(Serialize)]
+pub struct GetAllRes {
+    pub id: i32,
+    pub name: String,
+    pub recipes: i64,
+}
+
+#[axum_macros::debug_handler]
+pub async fn get_all(
+    claims: Claims,
+    Extension(pool): Extension<PgPool>,
+) -> Result<(StatusCode, Json<Vec<GetAllRes>>), (StatusCode, String)> {
+    let tags = sqlx::query_as!(
+        GetAllRes,
+        r#"
+            SELECT tag.id, tag.name, count(rt.id) AS "recipes!"
+            FROM tag
+            LEFT OUTER JOIN recipe_tag AS rt ON tag.id = rt.tag_id
+            WHERE tag.user_id = $1
+            GROUP BY tag.id
+            ORDER BY tag.name
+        "#,
+        claims.get_sub()
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed getting tags"))?;
+
+    Ok((StatusCode::OK, Json(tags)))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating tag");
+
+    validate_tag_name(
+        &payload.name,
+        None,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"INSERT INTO tag ( name, user_id ) VALUES ( $1, $2 )"#,
+        payload.name,
+        claims.get_sub()
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    pub name: String,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating tag");
+
+    validate_tag_name(
+        &payload.name,
+        Some(id),
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    sqlx::query!(
+        r#"
+            UPDATE tag SET name = $1
+            WHERE id = $2 AND user_id = $3
+            RETURNING id
+        "#,
+        payload.name,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Tag not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            DELETE FROM tag
+            WHERE id = $1 AND user_id = $2
+            RETURNING id
+        "#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| get_default_err("Failed deleting tag"))?
+    .ok_or((StatusCode::NOT_FOUND, "Tag not found".to_string()))?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,30 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Tag names have to be unique per user
+pub async fn validate_tag_name(
+    name: &str,
+    tag_id: Option<i32>,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let existing = sqlx::query!(
+        r#"
+            SELECT id FROM tag
+            WHERE lower(name) = lower($1) AND user_id = $2 AND id IS DISTINCT FROM $3
+        "#,
+        name,
+        user_id,
+        tag_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    if existing.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Tag already exists".to_string()));
+    }
+    Ok(())
+}
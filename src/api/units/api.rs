@@ -1,39 +1,216 @@
-use crate::api::auth::Claims;
-use axum::{http::StatusCode, Extension, Json};
-use serde::Serialize;
+use crate::api::{
+    auth::Claims,
+    global::{get_default_err, ValidatedJson},
+    units::{
+        quantity::{self, validate_quantity},
+        service::{get_units, resolve_conversion, validate_unit_name, validate_unit_unused},
+    },
+};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use validator::Validate;
 
 #[derive(Serialize)]
 pub struct GetRes {
     id: i32,
     name: String,
+    family: Option<String>,
+    #[serde(with = "quantity")]
+    factor: Decimal,
+    custom: bool,
 }
 
 #[axum_macros::debug_handler]
 pub async fn get_all(
-    _: Claims,
+    claims: Claims,
     Extension(pool): Extension<PgPool>,
 ) -> Result<(StatusCode, Json<Vec<GetRes>>), (StatusCode, String)> {
-    let units = sqlx::query!(r#"SELECT id, name FROM unit"#)
-        .fetch_all(&pool)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed getting units".to_string(),
-            )
-        })?;
+    let units = get_units(
+        claims.get_sub(),
+        get_default_err("Failed getting units"),
+        &pool,
+    )
+    .await?;
 
     Ok((
         StatusCode::OK,
         Json(
             units
                 .into_iter()
-                .map(|record| GetRes {
-                    id: record.id,
-                    name: record.name,
+                .map(|unit| GetRes {
+                    id: unit.id,
+                    name: unit.name,
+                    family: unit.family,
+                    factor: unit.factor,
+                    custom: unit.user_id.is_some(),
                 })
                 .collect(),
         ),
     ))
 }
+
+#[derive(Deserialize, Validate)]
+pub struct Conversion {
+    /// Unit the custom unit is expressed in
+    pub base_unit_id: i32,
+    /// Amount of the base unit one custom unit equals
+    #[serde(with = "quantity")]
+    #[validate(custom(
+        function = "validate_quantity",
+        message = "Factor has to be greater than 0 and less than 100000000"
+    ))]
+    pub factor: Decimal,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: String,
+    #[validate]
+    conversion: Option<Conversion>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn create(
+    claims: Claims,
+    ValidatedJson(payload): ValidatedJson<CreateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed creating unit");
+
+    validate_unit_name(
+        &payload.name,
+        None,
+        claims.get_sub(),
+        default_err.clone(),
+        &pool,
+    )
+    .await?;
+
+    let (family, factor) = match payload.conversion {
+        Some(ref conversion) => {
+            resolve_conversion(conversion, claims.get_sub(), default_err.clone(), &pool).await?
+        }
+        None => (None, Decimal::ONE),
+    };
+
+    sqlx::query!(
+        r#"INSERT INTO unit ( name, family, factor, user_id ) VALUES ( $1, $2, $3, $4 )"#,
+        payload.name,
+        family,
+        factor,
+        claims.get_sub()
+    )
+    .execute(&pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct UpdateReq {
+    #[validate(length(min = 1, message = "Name can't be empty"))]
+    name: Option<String>,
+    #[validate]
+    conversion: Option<Conversion>,
+}
+
+#[axum_macros::debug_handler]
+pub async fn update(
+    claims: Claims,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateReq>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed updating unit");
+
+    sqlx::query!(
+        r#"SELECT id FROM unit WHERE id = $1 AND user_id = $2"#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Unit not found".to_string()))?;
+
+    // Checked before renaming, so an invalid conversion doesn't leave a half updated unit
+    let conversion = match payload.conversion {
+        Some(ref conversion) => {
+            if conversion.base_unit_id == id {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "A unit can't be converted to itself".to_string(),
+                ));
+            }
+
+            // Stored quantities would silently change their meaning
+            validate_unit_unused(id, default_err.clone(), &pool).await?;
+
+            Some(
+                resolve_conversion(conversion, claims.get_sub(), default_err.clone(), &pool)
+                    .await?,
+            )
+        }
+        None => None,
+    };
+
+    if let Some(ref name) = payload.name {
+        validate_unit_name(name, Some(id), claims.get_sub(), default_err.clone(), &pool).await?;
+
+        sqlx::query!(r#"UPDATE unit SET name = $1 WHERE id = $2"#, name, id)
+            .execute(&pool)
+            .await
+            .map_err(|_| default_err.clone())?;
+    }
+
+    if let Some((family, factor)) = conversion {
+        sqlx::query!(
+            r#"UPDATE unit SET family = $1, factor = $2 WHERE id = $3"#,
+            family,
+            factor,
+            id
+        )
+        .execute(&pool)
+        .await
+        .map_err(|_| default_err)?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[axum_macros::debug_handler]
+pub async fn delete(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let default_err = get_default_err("Failed deleting unit");
+
+    sqlx::query!(
+        r#"SELECT id FROM unit WHERE id = $1 AND user_id = $2"#,
+        id,
+        claims.get_sub()
+    )
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| default_err.clone())?
+    .ok_or((StatusCode::NOT_FOUND, "Unit not found".to_string()))?;
+
+    // Ingredients and quantities restrict deleting their unit
+    sqlx::query!(r#"DELETE FROM unit WHERE id = $1"#, id)
+        .execute(&pool)
+        .await
+        .map_err(|err| {
+            let code = err.as_database_error().and_then(|err| err.code());
+            match code.as_deref() {
+                Some("23503") => (StatusCode::CONFLICT, "Unit is still in use".to_string()),
+                _ => default_err,
+            }
+        })?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,173 @@
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use serde::{de, Deserializer, Serializer};
+use std::{fmt, str::FromStr};
+use validator::ValidationError;
+
+/// Decimal places quantities are stored with
+pub const SCALE: u32 = 4;
+
+/// Quantities are stored as numeric(12, 4) and have to stay below this
+pub const MAX: Decimal = Decimal::from_parts(100_000_000, 0, 0, false, 0);
+
+/// Parses a quantity from a decimal ("0.5"), a fraction ("1/3") or a mixed fraction ("1 1/2")
+pub fn parse(value: &str) -> Option<Decimal> {
+    let value = value.trim();
+
+    let quantity = match value.split_once(' ') {
+        Some((whole, fraction)) => {
+            let whole = Decimal::from_str(whole).ok()?;
+            if !whole.fract().is_zero() {
+                return None;
+            }
+            whole.checked_add(parse_fraction(fraction.trim())?)?
+        }
+        None if value.contains('/') => parse_fraction(value)?,
+        None => Decimal::from_str(&value.replace(',', ".")).ok()?,
+    };
+
+    Some(quantity.round_dp(SCALE).normalize())
+}
+
+fn parse_fraction(value: &str) -> Option<Decimal> {
+    let (numerator, denominator) = value.split_once('/')?;
+    let numerator = Decimal::from_str(numerator.trim()).ok()?;
+    let denominator = Decimal::from_str(denominator.trim()).ok()?;
+    numerator.checked_div(denominator)
+}
+
+pub fn validate_quantity(value: &Decimal) -> Result<(), ValidationError> {
+    if value.is_sign_negative() || value.is_zero() || *value >= MAX {
+        return Err(ValidationError::new("invalid_quantity"));
+    }
+    Ok(())
+}
+
+/// Makes sure a computed quantity didn't overflow and can still be stored
+pub fn check_bounds(value: Option<Decimal>) -> Result<Decimal, (StatusCode, String)> {
+    value
+        .filter(|value| *value < MAX)
+        .ok_or((StatusCode::BAD_REQUEST, "Quantity is too large".to_string()))
+}
+
+pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&value.normalize())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(QuantityVisitor)
+}
+
+struct QuantityVisitor;
+
+impl<'de> de::Visitor<'de> for QuantityVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number, a decimal string or a fraction like \"1/3\"")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        // Go through the shortest string representation so 0.1 stays 0.1
+        parse(&value.to_string()).ok_or_else(|| E::custom("invalid quantity"))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        parse(value).ok_or_else(|| E::custom(format!("invalid quantity \"{}\"", value)))
+    }
+}
+
+pub mod option {
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => super::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super")] Decimal);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+
+    /// Tells a missing quantity apart from `null` like `global::nullable`
+    pub fn nullable<'de, D>(deserializer: D) -> Result<Option<Option<Decimal>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_fraction};
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn parses_decimals() {
+        assert_eq!(parse("0.5"), Some(dec("0.5")));
+        assert_eq!(parse(" 2 "), Some(dec("2")));
+        assert_eq!(parse("0,25"), Some(dec("0.25")));
+        assert_eq!(parse("1.23456"), Some(dec("1.2346")));
+    }
+
+    #[test]
+    fn parses_fractions() {
+        assert_eq!(parse("1/2"), Some(dec("0.5")));
+        assert_eq!(parse("1/3"), Some(dec("0.3333")));
+        assert_eq!(parse("1 1/2"), Some(dec("1.5")));
+        assert_eq!(parse("2  3/4"), Some(dec("2.75")));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("abc"), None);
+        assert_eq!(parse("1/0"), None);
+        assert_eq!(parse("1/"), None);
+        assert_eq!(parse("1.5 1/2"), None);
+        assert_eq!(parse("1 2"), None);
+        assert_eq!(parse("79228162514264337593543950335 1/1"), None);
+    }
+
+    #[test]
+    fn parses_fraction_parts() {
+        assert_eq!(parse_fraction("3/4"), Some(dec("0.75")));
+        assert_eq!(parse_fraction(" 1 / 4"), Some(dec("0.25")));
+        assert_eq!(parse_fraction("0.5/2"), Some(dec("0.25")));
+        assert_eq!(parse_fraction("3"), None);
+        assert_eq!(parse_fraction("1/0"), None);
+        assert_eq!(parse_fraction("a/2"), None);
+    }
+}
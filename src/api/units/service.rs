@@ -0,0 +1,293 @@
+use crate::api::units::{
+    api::Conversion,
+    quantity::{MAX, SCALE},
+};
+use axum::http::StatusCode;
+use rust_decimal::Decimal;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+pub struct Unit {
+    pub id: i32,
+    pub name: String,
+    pub family: Option<String>,
+    /// Amount of the family's base unit one unit equals
+    pub factor: Decimal,
+    /// Owner of a custom unit, global units don't have one
+    pub user_id: Option<Uuid>,
+}
+
+/// Global units and the custom units of the user and of everyone sharing a household with them
+pub async fn get_units(
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Unit>, (StatusCode, String)> {
+    sqlx::query_as!(
+        Unit,
+        r#"
+            SELECT id, name, family, factor, user_id FROM unit
+            WHERE user_id IS NULL OR user_id IN (SELECT co_members($1))
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await
+    .map_err(|_| default_err)
+}
+
+pub async fn validate_unit_id(
+    unit_id: i32,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+            SELECT id FROM unit
+            WHERE id = $1 AND (user_id IS NULL OR user_id IN (SELECT co_members($2)))
+        "#,
+        unit_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?
+    .ok_or((StatusCode::NOT_FOUND, "Unit not found".to_string()))
+    .map(|_| ())
+}
+
+/// Names have to be unique among the units available to a user
+pub async fn validate_unit_name(
+    name: &str,
+    unit_id: Option<i32>,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let existing = sqlx::query!(
+        r#"
+            SELECT id FROM unit
+            WHERE lower(name) = lower($1) AND (user_id IS NULL OR user_id IN (SELECT co_members($2)))
+            AND id IS DISTINCT FROM $3
+        "#,
+        name,
+        user_id,
+        unit_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| default_err)?;
+
+    if existing.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Unit already exists".to_string()));
+    }
+    Ok(())
+}
+
+/// Decimal places unit factors are stored with
+const FACTOR_SCALE: u32 = 6;
+
+/// Family and factor of a custom unit expressed in another unit
+pub async fn resolve_conversion(
+    conversion: &Conversion,
+    user_id: Uuid,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(Option<String>, Decimal), (StatusCode, String)> {
+    let units = get_units(user_id, default_err, pool).await?;
+    let base = find_unit(conversion.base_unit_id, &units)?;
+
+    let family = match base.family {
+        Some(ref family) => family.clone(),
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Unit with id {} can't be converted to other units",
+                    conversion.base_unit_id
+                ),
+            ))
+        }
+    };
+
+    // The factor is relative to the family's base unit, so it has to fit in there too
+    let factor = base
+        .factor
+        .checked_mul(conversion.factor)
+        .map(|factor| factor.round_dp(FACTOR_SCALE))
+        .filter(|factor| !factor.is_zero() && *factor < MAX)
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Factor in the base unit of the family has to be between 0.000001 and {}",
+                MAX
+            ),
+        ))?;
+
+    Ok((Some(family), factor))
+}
+
+/// Custom units can't change or go away while quantities are stored in them
+pub async fn validate_unit_unused(
+    unit_id: i32,
+    default_err: (StatusCode, String),
+    pool: &PgPool,
+) -> Result<(), (StatusCode, String)> {
+    let in_use = sqlx::query!(
+        r#"
+            SELECT EXISTS (SELECT 1 FROM ingredient WHERE unit_id = $1)
+            OR EXISTS (SELECT 1 FROM recipe_quantity WHERE unit_id = $1)
+            OR EXISTS (SELECT 1 FROM shopping_quantity WHERE unit_id = $1)
+            OR EXISTS (SELECT 1 FROM pantry WHERE unit_id = $1)
+            OR EXISTS (SELECT 1 FROM shopping_template_quantity WHERE unit_id = $1)
+            OR EXISTS (SELECT 1 FROM ingredient_price WHERE unit_id = $1) AS "in_use!"
+        "#,
+        unit_id
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|_| default_err)?
+    .in_use;
+
+    if in_use {
+        return Err((StatusCode::CONFLICT, "Unit is still in use".to_string()));
+    }
+    Ok(())
+}
+
+/// Converts a quantity between units of the same family, `density` (g/ml) allows converting
+/// between mass and volume, `None` if they can't be converted or the result overflows
+pub fn convert(
+    quantity: Decimal,
+    from: &Unit,
+    to: &Unit,
+    density: Option<Decimal>,
+) -> Option<Decimal> {
+    if from.id == to.id {
+        return Some(quantity);
+    }
+
+    let base = quantity.checked_mul(from.factor)?;
+    let base = match (from.family.as_deref()?, to.family.as_deref()?, density) {
+        (from, to, _) if from == to => base,
+        ("volume", "mass", Some(density)) => base.checked_mul(density)?,
+        ("mass", "volume", Some(density)) => base.checked_div(density)?,
+        _ => return None,
+    };
+
+    base.checked_div(to.factor)
+        .map(|converted| converted.round_dp(SCALE).normalize())
+}
+
+/// Expresses a quantity in the largest global unit of its family it's at least one of,
+/// so 1250 g become 1.25 kg
+pub fn normalize<'a>(quantity: Decimal, unit: &'a Unit, units: &'a [Unit]) -> (Decimal, &'a Unit) {
+    let family = match unit.family {
+        Some(ref family) => family,
+        None => return (quantity, unit),
+    };
+
+    let base = match quantity.checked_mul(unit.factor) {
+        Some(base) => base,
+        None => return (quantity, unit),
+    };
+    let target = units
+        .iter()
+        .filter(|u| u.user_id.is_none() && u.family.as_ref() == Some(family) && base >= u.factor)
+        .max_by_key(|u| u.factor)
+        .unwrap_or(unit);
+
+    match convert(quantity, unit, target, None) {
+        Some(converted) => (converted, target),
+        None => (quantity, unit),
+    }
+}
+
+/// Scales a quantity to another number of servings, `None` if the result overflows
+pub fn scale_quantity(quantity: Decimal, servings: i32, target_servings: i32) -> Option<Decimal> {
+    if servings == target_servings {
+        return Some(quantity);
+    }
+
+    let scaled = quantity
+        .checked_mul(Decimal::from(target_servings))?
+        .checked_div(Decimal::from(servings))?;
+
+    // Quantities can't be 0, so keep at least the smallest storable amount
+    Some(
+        scaled
+            .round_dp(SCALE)
+            .max(Decimal::new(1, SCALE))
+            .normalize(),
+    )
+}
+
+pub fn find_unit(id: i32, units: &[Unit]) -> Result<&Unit, (StatusCode, String)> {
+    units.iter().find(|u| u.id == id).ok_or((
+        StatusCode::NOT_FOUND,
+        format!("Unit with id {} not found", id),
+    ))
+}
+
+/// Makes sure quantities in a unit can be merged with the ingredient's default unit
+pub fn check_unit(
+    unit_id: i32,
+    ingredient_id: i32,
+    ingredient_unit_id: i32,
+    density: Option<Decimal>,
+    units: &[Unit],
+) -> Result<(), (StatusCode, String)> {
+    let from = find_unit(unit_id, units)?;
+    let to = find_unit(ingredient_unit_id, units)?;
+
+    convert(Decimal::ONE, from, to, density)
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unit with id {} can't be converted to the unit of ingredient with id {}",
+                unit_id, ingredient_id
+            ),
+        ))
+        .map(|_| ())
+}
+
+/// Sums up quantities in the given unit and normalizes the result,
+/// `None` if one of the quantities can't be converted
+pub fn sum<'a>(
+    quantities: impl IntoIterator<Item = (Decimal, i32)>,
+    unit: &'a Unit,
+    density: Option<Decimal>,
+    units: &'a [Unit],
+) -> Option<(Decimal, &'a Unit)> {
+    let mut total = Decimal::ZERO;
+    for (quantity, unit_id) in quantities {
+        let from = units.iter().find(|u| u.id == unit_id)?;
+        total = total.checked_add(convert(quantity, from, unit, density)?)?;
+    }
+    Some(normalize(total, unit, units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scale_quantity;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn keeps_exact_scaled_quantities() {
+        assert_eq!(scale_quantity(dec("0.5"), 4, 6), Some(dec("0.75")));
+        assert_eq!(scale_quantity(dec("0.5"), 2, 1), Some(dec("0.25")));
+        assert_eq!(scale_quantity(dec("1"), 3, 1), Some(dec("0.3333")));
+        assert_eq!(scale_quantity(dec("0.0001"), 4, 1), Some(dec("0.0001")));
+    }
+
+    #[test]
+    fn rejects_overflowing_quantities() {
+        assert_eq!(scale_quantity(Decimal::MAX, 1, 2), None);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::routes::routes;
+use crate::{
+    api::{shopping, shopping_templates},
+    routes::routes,
+};
 use axum::Server;
 use sqlx::PgPool;
 use std::{env, net::SocketAddr};
@@ -18,6 +21,9 @@ pub async fn init() {
         .parse()
         .expect("BIND_ADDRESS is invalid");
 
+    tokio::spawn(shopping_templates::recurrence::run(pool.clone()));
+    tokio::spawn(shopping::cleanup::run(pool.clone()));
+
     println!("Server started on {}", bind_address);
 
     Server::bind(&bind_address)
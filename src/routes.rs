@@ -1,7 +1,7 @@
 use crate::api;
 use axum::{
     http::header::{AUTHORIZATION, CONTENT_TYPE},
-    routing::{get, patch, post, put},
+    routing::{delete, get, patch, post, put},
     Extension, Router,
 };
 use sqlx::PgPool;
@@ -18,12 +18,15 @@ pub fn routes(pool: PgPool) -> Router {
 
     let recipes_api = Router::new()
         .route("/", get(api::recipes::get_all).post(api::recipes::create))
+        .route("/suggestions", get(api::recipes::suggest))
         .route(
             "/:id",
             get(api::recipes::get)
                 .put(api::recipes::update)
                 .delete(api::recipes::delete),
-        );
+        )
+        .route("/:id/steps/:step_id", patch(api::recipes::sort_step))
+        .route("/:id/substitute", post(api::recipes::substitute));
 
     let ingredients_api = Router::new()
         .route(
@@ -35,38 +38,162 @@ pub fn routes(pool: PgPool) -> Router {
             put(api::ingredients::update)
                 .patch(api::ingredients::sort)
                 .delete(api::ingredients::delete),
-        );
+        )
+        .route(
+            "/:id/prices",
+            get(api::prices::get_all).post(api::prices::create),
+        )
+        .route("/:id/prices/:price_id", delete(api::prices::delete));
 
     let shopping_api = Router::new()
         .route("/", get(api::shopping::get_all).post(api::shopping::create))
+        .route("/generate", post(api::shopping::generate))
         .route(
             "/:id",
-            get(api::shopping::get).delete(api::shopping::delete),
+            get(api::shopping::get)
+                .put(api::shopping::update)
+                .delete(api::shopping::delete),
+        )
+        .route("/:id/duplicate", post(api::shopping::duplicate))
+        .route("/:id/merge", post(api::shopping::merge))
+        .route("/:id/events", get(api::shopping::events))
+        .route("/:id/finish", post(api::shopping::finish))
+        .route("/:id/sync", post(api::shopping::sync))
+        .route(
+            "/:id/members",
+            get(api::shopping::get_members).post(api::shopping::add_member),
+        )
+        .route(
+            "/:id/members/:user_id",
+            put(api::shopping::update_member).delete(api::shopping::delete_member),
         )
         .route(
             "/:id/recipe/:recipe_id",
             post(api::shopping::add_recipe).delete(api::shopping::delete_recipe),
         )
+        .route("/:id/ingredients", patch(api::shopping::check_ingredients))
+        .route("/:id/ingredients/store", put(api::shopping::assign_store))
         .route(
             "/:id/ingredient/:ingredient_id",
             post(api::shopping::add_ingredient)
                 .patch(api::shopping::check_ingredient)
                 .delete(api::shopping::delete_ingredient),
         )
+        .route(
+            "/:id/ingredient/:ingredient_id/substitute",
+            post(api::shopping::substitute),
+        )
         .route(
             "/:id/quantity/:quantity_id",
             put(api::shopping::update_quantity).delete(api::shopping::delete_quantity),
         );
 
-    let units_api = Router::new().route("/", get(api::units::get_all));
+    let shopping_templates_api = Router::new()
+        .route(
+            "/",
+            get(api::shopping_templates::get_all).post(api::shopping_templates::create),
+        )
+        .route(
+            "/:id",
+            get(api::shopping_templates::get)
+                .put(api::shopping_templates::update)
+                .delete(api::shopping_templates::delete),
+        )
+        .route("/:id/apply", post(api::shopping_templates::apply));
+
+    let meal_plans_api = Router::new()
+        .route(
+            "/",
+            get(api::meal_plans::get_all).post(api::meal_plans::create),
+        )
+        .route("/nutrition", get(api::meal_plans::nutrition))
+        .route(
+            "/:id",
+            put(api::meal_plans::update).delete(api::meal_plans::delete),
+        )
+        .route("/:id/copy", post(api::meal_plans::copy));
+
+    let pantry_api = Router::new()
+        .route("/", get(api::pantry::get_all).post(api::pantry::create))
+        .route("/:id", put(api::pantry::update).delete(api::pantry::delete));
+
+    let households_api = Router::new()
+        .route(
+            "/",
+            get(api::households::get_all).post(api::households::create),
+        )
+        .route(
+            "/:id",
+            put(api::households::update).delete(api::households::delete),
+        )
+        .route("/:id/import", post(api::households::import))
+        .route(
+            "/:id/members",
+            get(api::households::get_members).post(api::households::add_member),
+        )
+        .route(
+            "/:id/members/:user_id",
+            put(api::households::update_member).delete(api::households::delete_member),
+        );
+
+    let units_api = Router::new()
+        .route("/", get(api::units::get_all).post(api::units::create))
+        .route("/:id", put(api::units::update).delete(api::units::delete));
+
+    let tags_api = Router::new()
+        .route("/", get(api::tags::get_all).post(api::tags::create))
+        .route("/:id", put(api::tags::update).delete(api::tags::delete));
+
+    let categories_api = Router::new()
+        .route(
+            "/",
+            get(api::categories::get_all).post(api::categories::create),
+        )
+        .route(
+            "/:id",
+            put(api::categories::update).delete(api::categories::delete),
+        );
+
+    let stores_api = Router::new()
+        .route("/", get(api::stores::get_all).post(api::stores::create))
+        .route(
+            "/:id",
+            get(api::stores::get)
+                .put(api::stores::update)
+                .delete(api::stores::delete),
+        );
+
+    let substitutions_api = Router::new()
+        .route(
+            "/",
+            get(api::substitutions::get_all).post(api::substitutions::create),
+        )
+        .route(
+            "/:id",
+            put(api::substitutions::update).delete(api::substitutions::delete),
+        );
+
+    let search_api = Router::new()
+        .route("/recipes", get(api::search::search_recipes))
+        .route("/ingredients", get(api::search::search_ingredients));
 
     Router::new()
         .nest("/api/users", users_api)
         .nest("/api/recipes", recipes_api)
         .nest("/api/ingredients", ingredients_api)
         .nest("/api/shopping", shopping_api)
+        .nest("/api/shopping_templates", shopping_templates_api)
+        .nest("/api/meal_plans", meal_plans_api)
+        .nest("/api/pantry", pantry_api)
+        .nest("/api/households", households_api)
         .nest("/api/units", units_api)
+        .nest("/api/tags", tags_api)
+        .nest("/api/categories", categories_api)
+        .nest("/api/stores", stores_api)
+        .nest("/api/substitutions", substitutions_api)
+        .nest("/api/search", search_api)
         .layer(Extension(pool))
+        .layer(Extension(api::shopping::ShoppingEvents::default()))
         .layer(
             CorsLayer::new()
                 .allow_origin([